@@ -0,0 +1,22 @@
+use std::str::FromStr;
+
+// how a neighbor places the monster of an ambush on our board
+#[derive(Clone, Copy)]
+pub enum AmbushModel {
+    // where it costs us the most points
+    WorstCase,
+    // every possible placement with the same probability
+    Random,
+}
+
+impl FromStr for AmbushModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "worst-case" => Ok(Self::WorstCase),
+            "random" => Ok(Self::Random),
+            _ => Err(format!("unknown ambush model {}", s)),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    game::{PlayerBoard, PlayerTerrain, Season},
+    map::{self, Map, MapTerrain},
+    mask::Mask,
+    names, protocol,
+    scoring::Scoring,
+    search::{find_best_moves, GameState, InitialState, SearchLimits},
+    transposition::TranspositionTable,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Protocol(protocol::Error),
+    Format(String),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read board: {}", err),
+            Self::Protocol(err) => write!(f, "invalid board: {}", err),
+            Self::Format(msg) => write!(f, "invalid board: {}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<protocol::Error> for Error {
+    fn from(err: protocol::Error) -> Self {
+        Self::Protocol(err)
+    }
+}
+
+// everything known about a turn of the game
+pub struct Position {
+    pub degrees: [Scoring; 4],
+    pub board: PlayerBoard,
+    pub map: Map,
+    pub season: Season,
+    // cards drawn in this season before the current card
+    pub used_cards: Vec<String>,
+    // by default the time of the used cards and the current card
+    pub timer: Option<u16>,
+    pub card: String,
+    pub on_ruin: bool,
+}
+
+// the degrees A to D by id, german or english name
+pub fn find_degrees(names: &[String]) -> std::result::Result<[Scoring; 4], String> {
+    let degrees = names
+        .iter()
+        .map(|name| {
+            names::find(name)
+                .and_then(|card| names::find_degree(card.id))
+                .map(|degree| degree.scoring)
+                .ok_or_else(|| format!("unknown degree {}", name))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    degrees
+        .try_into()
+        .map_err(|_| "expected four degrees".to_string())
+}
+
+// either json sent by the server (the fields of a newTurn event, the whole event or a recorded
+// entry, for a recording the board of our last turn and the map of the first one is used) or a
+// text grid with one row per line:
+// 'f' forest, 'v' village, 'a' farm, '~' water, 'x' monster, 'm' mountain, 'r' ruin,
+// 'w' wasteland and '.' or ' ' for empty cells
+pub fn load_board(path: impl AsRef<Path>) -> Result<(PlayerBoard, Map)> {
+    let content = fs::read_to_string(path)?;
+    let trimmed = content.trim_start();
+
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return board_from_grid(&map::grid_rows(&content));
+    }
+
+    // a recording has one json object per line
+    let values = match serde_json::from_str(trimmed) {
+        Ok(value) => vec![value],
+        Err(_) => content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+    };
+
+    // the boards of the neighbors we placed an ambush on are not ours, we are the player of the
+    // first turn which is not an ambush
+    let own_id = values
+        .iter()
+        .filter_map(turn_of)
+        .find(|&(_, card)| !names::is_ambush(card))
+        .map(|(player_id, _)| player_id);
+    let mut boards = values
+        .iter()
+        .filter(|value| own_id.is_none() || turn_of(value).map(|(id, _)| id) == own_id)
+        .filter_map(find_fields);
+    let first = boards
+        .next()
+        .ok_or_else(|| Error::Format("no board found".into()))?;
+    let last = boards.next_back().unwrap_or(first);
+
+    // covered ruins are not shown anymore, so the map is taken from the first board
+    let (board, _) = protocol::parse_board(last)?;
+    let (_, map) = protocol::parse_board(first)?;
+    Ok((board, map))
+}
+
+// the player id and the current card of a newTurn event, the whole event or a recorded entry
+fn turn_of(value: &Value) -> Option<(&str, &str)> {
+    let data = value.get("data").unwrap_or(value);
+    let player_id = data.get("playerId")?.as_str()?;
+    let card = data.get("exploreCard")?.get("name")?.as_str()?;
+    Some((player_id, card))
+}
+
+// the fields of a board are an array, the fields of a sent turn are an object
+fn find_fields(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(_) => Some(value),
+        _ => value
+            .get("fields")
+            .or_else(|| value.get("data").and_then(|data| data.get("fields")))
+            .filter(|fields| fields.is_array()),
+    }
+}
+
+fn board_from_grid(rows: &[&str]) -> Result<(PlayerBoard, Map)> {
+    if rows.len() != Mask::SIZE as usize {
+        return Err(Error::Format(format!(
+            "expected {} rows, got {}",
+            Mask::SIZE,
+            rows.len()
+        )));
+    }
+
+    let mut cells = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        let mut row: Vec<_> = row.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if row.len() > Mask::SIZE as usize {
+            return Err(Error::Format(format!("row {} is too long", y + 1)));
+        }
+        if let Some(c) = row.iter().find(|c| !".fva~xmrw ".contains(**c)) {
+            return Err(Error::Format(format!(
+                "unknown terrain '{}' in row {}",
+                c,
+                y + 1
+            )));
+        }
+
+        row.resize(Mask::SIZE as usize, '.');
+        cells.extend(row);
+    }
+
+    let board = PlayerBoard::new_with(|i| match cells[i as usize] {
+        'f' => Some(PlayerTerrain::Forest),
+        'v' => Some(PlayerTerrain::Village),
+        'a' => Some(PlayerTerrain::Farm),
+        '~' => Some(PlayerTerrain::Water),
+        'x' => Some(PlayerTerrain::Monster),
+        _ => None,
+    });
+    let map = Map::new_with(|i| match cells[i as usize] {
+        'm' => Some(MapTerrain::Mountain),
+        'r' => Some(MapTerrain::Ruin),
+        'w' => Some(MapTerrain::Wasteland),
+        _ => None,
+    });
+
+    Ok((board, map))
+}
+
+// the board in the format of `load_board`
+fn board_grid(board: &PlayerBoard, map: &Map) -> String {
+    let terrains = [
+        (board.get_cells(PlayerTerrain::Forest), 'f'),
+        (board.get_cells(PlayerTerrain::Village), 'v'),
+        (board.get_cells(PlayerTerrain::Farm), 'a'),
+        (board.get_cells(PlayerTerrain::Water), '~'),
+        (board.get_cells(PlayerTerrain::Monster), 'x'),
+        (map.mountain, 'm'),
+        (map.wasteland, 'w'),
+        (map.ruin, 'r'),
+    ];
+
+    let mut grid = String::new();
+    for y in 0..Mask::SIZE {
+        for x in 0..Mask::SIZE {
+            let c = terrains
+                .iter()
+                .find(|(mask, _)| mask.contains(Mask::cell(x, y)))
+                .map_or('.', |&(_, c)| c);
+            grid.push(c);
+        }
+        grid.push('\n');
+    }
+
+    grid
+}
+
+// prints the best turns for the current card with their expected score
+pub fn analyze(position: &Position, top: usize, limits: &SearchLimits) {
+    let initial_state = InitialState::new(position.degrees, position.map);
+    let mut state = GameState::new(&initial_state);
+
+    // every season shuffles another ambush into the deck
+    let seasons = [Season::Spring, Season::Summer, Season::Fall, Season::Winter];
+    for season in seasons.into_iter().filter(|&s| s <= position.season) {
+        state.new_season(season);
+    }
+
+    for card in position.used_cards.iter().chain([&position.card]) {
+        if let Err(err) = state.reveal_card(card) {
+            println!("{}", err);
+            return;
+        }
+    }
+    state.set_on_ruin(position.on_ruin);
+    if let Some(timer) = position.timer {
+        state.set_season_timer(timer);
+    }
+    state.new_board(position.board);
+
+    println!("{}", board_grid(&position.board, &position.map));
+    println!(
+        "{:?}, timer {}/{}, card {}{}",
+        position.season,
+        state.season_timer(),
+        position.season.time(),
+        names::describe(&position.card),
+        if position.on_ruin { " on a ruin" } else { "" }
+    );
+
+    let (ranking, statistics) =
+        find_best_moves(&state, &position.card, limits, &TranspositionTable::new());
+    println!(
+        "Positions evaluated: {}, depth reached: {}{}",
+        statistics.positions_evaluated,
+        statistics.depth_reached,
+        if statistics.end_reached {
+            " (end of the game)"
+        } else {
+            ""
+        }
+    );
+
+    for (i, (turn, score)) in ranking.iter().take(top).enumerate() {
+        println!(
+            "\n#{} {:?} with {} gold, expected score {:.2}{:?}",
+            i + 1,
+            turn.terrain,
+            turn.gold,
+            score,
+            turn.cells
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn fields(landscape: impl Fn(u8) -> &'static str) -> Value {
+        (0..Mask::CELL_COUNT)
+            .map(|idx| json!({ "landscape": landscape(idx) }))
+            .collect()
+    }
+
+    // loads the events as a recording with one json object per line
+    fn load_recording(name: &str, events: &[Value]) -> Result<(PlayerBoard, Map)> {
+        let recording: Vec<_> = events.iter().map(Value::to_string).collect();
+        let path = env::temp_dir().join(format!(
+            "cartographers-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, recording.join("\n")).unwrap();
+        let loaded = load_board(&path);
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    fn new_turn(player_id: &str, card: &str, fields: Value) -> Value {
+        json!({
+            "event": "newTurn",
+            "direction": "received",
+            "data": { "playerId": player_id, "exploreCard": { "name": card }, "fields": fields },
+        })
+    }
+
+    #[test]
+    fn grid_keeps_empty_rows() {
+        let mut rows = vec!["f"; Mask::SIZE as usize];
+        rows[1] = "           ";
+        rows[2] = "v";
+        let path = env::temp_dir().join(format!("cartographers-grid-{}.txt", std::process::id()));
+        fs::write(&path, rows.join("\n") + "\n\n").unwrap();
+        let loaded = load_board(&path);
+        fs::remove_file(&path).unwrap();
+
+        let (board, _) = loaded.unwrap();
+        assert_eq!(board.get_cells(PlayerTerrain::Village), Mask::cell(0, 2));
+        assert!(!board
+            .get_cells(PlayerTerrain::Forest)
+            .contains(Mask::cell(0, 1)));
+    }
+
+    #[test]
+    fn recording_keeps_covered_ruins() {
+        let first = fields(|idx| if idx == 16 { "Ruin" } else { "EMPTY" });
+        let last = fields(|idx| if idx == 16 { "FOREST" } else { "EMPTY" });
+        let events =
+            [first, last].map(|fields| json!({ "event": "newTurn", "data": { "fields": fields } }));
+
+        let (board, map) = load_recording("ruins", &events).unwrap();
+        assert_eq!(board.get_cells(PlayerTerrain::Forest), Mask::cell_idx(16));
+        assert_eq!(map.ruin, Mask::cell_idx(16));
+    }
+
+    #[test]
+    fn recording_ending_in_a_neighbor_ambush() {
+        let events = [
+            new_turn("me", "weiler", fields(|_| "EMPTY")),
+            new_turn(
+                "me",
+                "ackerland",
+                fields(|idx| if idx == 0 { "VILLAGE" } else { "EMPTY" }),
+            ),
+            new_turn(
+                "neighbor",
+                "koboldansturm",
+                fields(|idx| if idx == 5 { "FARM" } else { "EMPTY" }),
+            ),
+        ];
+
+        let (board, _) = load_recording("neighbor", &events).unwrap();
+        assert_eq!(board.get_cells(PlayerTerrain::Village), Mask::cell_idx(0));
+        assert!(board.get_cells(PlayerTerrain::Farm).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use crate::{map::Map, scoring::Score, search::SearchLimits, simulation};
+
+pub struct ScoreStatistics {
+    pub count: usize,
+    pub mean: f32,
+    pub median: f32,
+    pub std_dev: f32,
+}
+
+impl ScoreStatistics {
+    pub fn new(scores: &[Score]) -> Self {
+        let mut scores = scores.to_vec();
+        scores.sort_unstable();
+
+        let count = scores.len();
+        if count == 0 {
+            return Self {
+                count,
+                mean: 0.0,
+                median: 0.0,
+                std_dev: 0.0,
+            };
+        }
+
+        let mean = scores.iter().map(|&s| s as f32).sum::<f32>() / count as f32;
+        let median = if count.is_multiple_of(2) {
+            (scores[count / 2 - 1] + scores[count / 2]) as f32 / 2.0
+        } else {
+            scores[count / 2] as f32
+        };
+        let variance = scores
+            .iter()
+            .map(|&s| (s as f32 - mean).powi(2))
+            .sum::<f32>()
+            / count as f32;
+
+        Self {
+            count,
+            mean,
+            median,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+// plays the games with the seeds first_seed..first_seed + games and prints the statistics
+pub fn run(map: Map, games: u64, first_seed: u64, limits: &SearchLimits) {
+    let mut scores = Vec::new();
+    // per degree the points it scored and the final scores of all games it was part of
+    let mut degree_points: BTreeMap<&str, Vec<Score>> = BTreeMap::new();
+    let mut degree_finals: BTreeMap<&str, Vec<Score>> = BTreeMap::new();
+
+    for seed in first_seed..first_seed + games {
+        let result = simulation::play_game(map, seed, limits);
+        let score = result.score();
+        println!(
+            "Game {} (seed {}): {} [{}]",
+            seed - first_seed + 1,
+            seed,
+            score,
+            result.degrees.map(|d| d.name.english).join(", ")
+        );
+
+        let mut points = [0; 4];
+        for (_, season_score) in &result.season_scores {
+            for (idx, p) in season_score.degrees {
+                points[idx] += p;
+            }
+        }
+
+        for (degree, p) in result.degrees.iter().zip(points) {
+            let name = degree.name.english;
+            degree_points.entry(name).or_default().push(p);
+            degree_finals.entry(name).or_default().push(score);
+        }
+
+        scores.push(score);
+    }
+
+    let total = ScoreStatistics::new(&scores);
+    println!();
+    println!(
+        "Games: {}, mean: {:.2}, median: {:.1}, std dev: {:.2}",
+        total.count, total.mean, total.median, total.std_dev
+    );
+
+    println!();
+    println!(
+        "{:<20} {:>5} {:>12} {:>12} {:>12}",
+        "degree", "games", "mean points", "points sd", "mean final"
+    );
+    for (name, points) in &degree_points {
+        let points = ScoreStatistics::new(points);
+        let finals = ScoreStatistics::new(&degree_finals[name]);
+        println!(
+            "{:<20} {:>5} {:>12.2} {:>12.2} {:>12.2}",
+            name, points.count, points.mean, points.std_dev, finals.mean
+        );
+    }
+}
@@ -61,7 +61,7 @@ impl ExploreCard {
     }
 }
 
-// Note: english card names are not available at the moment
+// card names are the ids used by the server, see `names` for the german and english names
 
 pub fn ackerland() -> ExploreCard {
     ExploreCardBuilder::new("ackerland", 1, &[Farm])
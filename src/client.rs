@@ -0,0 +1,257 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    thread,
+    time::Duration,
+};
+
+use tungstenite::http::Uri;
+
+use crate::{
+    record::{Direction, Recorder},
+    socketio::{self, Error, Result, Socket, SocketIOExt, Transport},
+};
+
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+// a connection to the game server, which is reestablished when it is lost
+pub struct Client {
+    uri: Uri,
+    namespace: String,
+    transport: Transport,
+    name: String,
+    socket: Socket,
+    reconnected: bool,
+    // the reconnect failed, the connection is lost for good
+    closed: bool,
+    next_ack_id: u64,
+    // events which were not acknowledged yet
+    pending_acks: HashMap<u64, String>,
+    // messages which could not be written, they are sent again after the reconnect
+    unsent: VecDeque<String>,
+    recorder: Option<Recorder>,
+}
+
+impl Client {
+    pub fn connect(
+        uri: Uri,
+        namespace: String,
+        transport: Transport,
+        name: String,
+        start: bool,
+        recorder: Option<Recorder>,
+    ) -> Result<Self> {
+        let socket = socketio::connect(&uri, &namespace, transport)?;
+        println!("Connected with session id {}", socket.handshake().sid);
+
+        let mut client = Self {
+            uri,
+            namespace,
+            transport,
+            name,
+            socket,
+            reconnected: false,
+            closed: false,
+            next_ack_id: 0,
+            pending_acks: HashMap::new(),
+            unsent: VecDeque::new(),
+            recorder,
+        };
+
+        client.write_event("enterGame", client.name.clone())?;
+
+        if start {
+            client.write_event("startGame", "")?;
+        }
+
+        Ok(client)
+    }
+
+    // whether the connection was reestablished since the last call, all state sent by the server
+    // before might be outdated
+    pub fn take_reconnected(&mut self) -> bool {
+        std::mem::take(&mut self.reconnected)
+    }
+
+    // sends an event and logs when the server confirms it
+    pub fn write_acknowledged_event(
+        &mut self,
+        event: impl Into<String>,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let event = event.into();
+        let id = self.next_ack_id;
+        self.next_ack_id += 1;
+
+        // after a reconnect the event was sent again with the new session, which acknowledges it
+        self.write_json_event_with_ack(event.clone(), data, id)?;
+        self.pending_acks.insert(id, event);
+        Ok(())
+    }
+
+    // whether the reconnect failed, reading or writing again fails the same way
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn record(&mut self, direction: Direction, event: &str, data: Option<&serde_json::Value>) {
+        if let Some(recorder) = &mut self.recorder {
+            // a broken recording should not end the game
+            if let Err(err) = recorder.record(direction, event, data) {
+                println!("Cannot record event {}: {}", event, err);
+            }
+        }
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let mut delay = Duration::from_secs(1);
+        let mut attempt = 1;
+
+        loop {
+            thread::sleep(delay);
+
+            match self.start_session() {
+                Ok(()) => {
+                    println!("Reconnected after {} attempts", attempt);
+                    return Ok(());
+                }
+                Err(err) if attempt < MAX_RECONNECT_ATTEMPTS => {
+                    println!("Reconnect attempt {} failed: {}", attempt, err);
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    attempt += 1;
+                }
+                Err(err) => {
+                    self.closed = true;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    // connects again, enters the game and sends the messages which were not written before, the
+    // new socket is written directly, so a failure does not start another reconnect
+    fn start_session(&mut self) -> Result<()> {
+        let mut socket = socketio::connect(&self.uri, &self.namespace, self.transport)?;
+        let mut session = Session {
+            socket: &mut socket,
+            namespace: &self.namespace,
+        };
+
+        let name = serde_json::Value::String(self.name.clone());
+        session.write_json_event("enterGame", &name)?;
+        for msg in &self.unsent {
+            session.write_raw_message(msg.as_str())?;
+        }
+
+        println!(
+            "Session id {} replaces {}, {} messages sent again",
+            socket.handshake().sid,
+            self.socket.handshake().sid,
+            self.unsent.len()
+        );
+        self.socket = socket;
+        self.unsent.clear();
+        // the old session is gone, so its events are never acknowledged
+        for event in self.pending_acks.values() {
+            println!("{} was not acknowledged before the reconnect", event);
+        }
+        self.pending_acks.clear();
+        self.reconnected = true;
+        self.record(Direction::Reconnected, "", None);
+        // the events sent again may still be acknowledged
+        self.record(Direction::Sent, "enterGame", Some(&name));
+
+        Ok(())
+    }
+}
+
+// a new socket with the namespace of the client, which is not reconnected on errors
+struct Session<'a> {
+    socket: &'a mut Socket,
+    namespace: &'a str,
+}
+
+impl SocketIOExt for Session<'_> {
+    fn read_raw_message(&mut self) -> Result<String> {
+        self.socket.read_raw_message()
+    }
+
+    fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+        self.socket.write_raw_message(msg)
+    }
+
+    fn namespace(&self) -> &str {
+        self.namespace
+    }
+}
+
+impl SocketIOExt for Client {
+    fn read_raw_message(&mut self) -> Result<String> {
+        loop {
+            match self.socket.read_raw_message() {
+                Ok(msg) => return Ok(msg),
+                Err(err) => {
+                    println!("Connection lost: {}", err);
+                    self.reconnect()?;
+                }
+            }
+        }
+    }
+
+    fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+        let msg = msg.into();
+        match self.socket.write_raw_message(msg.as_str()) {
+            Ok(()) => Ok(()),
+            // sending it again would fail the same way
+            Err(err @ Error::PayloadTooLarge { .. }) => Err(err),
+            Err(err) => {
+                println!("Connection lost: {}", err);
+                // the reconnect sends the message again
+                self.unsent.push_back(msg);
+                self.reconnect()
+            }
+        }
+    }
+
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        self.socket.read_raw_binary()
+    }
+
+    // an attachment is not sent again after a reconnect, the packet it belongs to is incomplete
+    fn write_raw_binary(&mut self, data: &[u8]) -> Result<()> {
+        self.socket.write_raw_binary(data)
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn on_disconnect(&mut self) -> Result<()> {
+        println!("Disconnected by the server");
+        self.reconnect()
+    }
+
+    fn on_event(
+        &mut self,
+        sent: bool,
+        event: &str,
+        data: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        let direction = if sent {
+            Direction::Sent
+        } else {
+            Direction::Received
+        };
+        self.record(direction, event, data);
+
+        Ok(())
+    }
+
+    fn on_ack(&mut self, id: u64, _data: serde_json::Value) -> Result<()> {
+        if let Some(event) = self.pending_acks.remove(&id) {
+            println!("Server acknowledged {}", event);
+        }
+
+        Ok(())
+    }
+}
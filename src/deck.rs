@@ -1,19 +1,24 @@
-// a representation of all cards left in the deck
-#[derive(Clone, Copy)]
+// the cards left in the deck of the current season: the explore cards, the ruins and the ambushes
+// shuffled in, it is not known which of the ambushes not drawn yet they are
+#[derive(Clone, Copy, Hash)]
 pub struct Deck {
     explore_cards_mask: u16,
+    ruin_cards_mask: u8,
+    // the ambushes not drawn in the whole game, either in the deck or still in the ambush pile
     monster_cards_mask: u16,
     monsters_in_deck: u8,
 }
 
 impl Deck {
     const EXPLORE_COUNT: u16 = 11;
+    const RUIN_COUNT: u16 = 2;
     const MONSTER_COUNT: u16 = 8;
 
     pub fn empty() -> Self {
         Self {
             explore_cards_mask: 0,
-            monster_cards_mask: 0,
+            ruin_cards_mask: 0,
+            monster_cards_mask: (1 << Self::MONSTER_COUNT) - 1,
             monsters_in_deck: 0,
         }
     }
@@ -22,27 +27,63 @@ impl Deck {
         self.explore_cards_mask ^= 1 << idx;
     }
 
+    // the server lists a ruin again after an ambush, so removing it twice has no effect
+    pub fn remove_ruin_card(&mut self, idx: u16) {
+        self.ruin_cards_mask &= !(1 << idx);
+    }
+
+    // an ambush revealed again, e.g. after a reconnect, was already removed and has no effect
     pub fn remove_monster_card(&mut self, idx: u16) {
-        self.monster_cards_mask ^= 1 << idx;
-        self.monsters_in_deck -= 1;
+        if self.monster_cards_mask & (1 << idx) != 0 {
+            self.monster_cards_mask &= !(1 << idx);
+            self.monsters_in_deck = self.monsters_in_deck.saturating_sub(1);
+        }
     }
 
     pub fn new_season(&mut self) {
-        // shuffle in all explore card and ruins and one additional monster card
+        // shuffle in all explore card and ruins and one additional monster card from the pile, the
+        // ambushes not drawn last season stay in the deck
         *self = Self {
             explore_cards_mask: (1 << Self::EXPLORE_COUNT) - 1,
-            monster_cards_mask: (1 << Self::MONSTER_COUNT) - 1,
+            ruin_cards_mask: (1 << Self::RUIN_COUNT) - 1,
+            monster_cards_mask: self.monster_cards_mask,
             monsters_in_deck: self.monsters_in_deck + 1,
         }
     }
 
+    // every card which can be drawn next with its probability and the deck after drawing it, with
+    // T cards in the deck:
+    // - each explore card 1/T
+    // - a ruin R/T for R ruins in the deck, both ruins have the same effect
+    // - each possible ambush M/(T*K) for M ambushes in the deck and K ambushes not drawn in this
+    //   game, each of them is in the deck with the same probability
+    // the probabilities sum up to one, nothing can be drawn from an empty deck
     pub fn draw_cards(&self) -> impl Iterator<Item = (DrawnCard, f32, Self)> + '_ {
-        let e = self.explore_cards_mask.count_ones() as f32;
-        let m = self.monsters_in_deck as f32;
-        let total = e + m;
+        debug_assert!(
+            self.card_count() == 0
+                || (self.cards().map(|(_, prob, _)| prob).sum::<f32>() - 1.0).abs() < 1e-4,
+            "probabilities of the deck do not sum up to one"
+        );
 
-        let explore_prob = 1.0 / total;
+        self.cards()
+    }
 
+    fn card_count(&self) -> u32 {
+        self.explore_cards_mask.count_ones()
+            + self.ruin_cards_mask.count_ones()
+            + self.monsters_in_deck as u32
+    }
+
+    fn cards(&self) -> impl Iterator<Item = (DrawnCard, f32, Self)> + '_ {
+        debug_assert!(
+            self.monsters_in_deck as u32 <= self.monster_cards_mask.count_ones(),
+            "more ambushes in the deck than not drawn yet"
+        );
+
+        // all iterators are empty for an empty deck, its probabilities are never used
+        let total = self.card_count().max(1) as f32;
+
+        let explore_prob = 1.0 / total;
         let explore_iter = MaskIterator(self.explore_cards_mask).map(move |pos| {
             let mut new_deck = *self;
             new_deck.remove_explore_card(pos);
@@ -50,27 +91,35 @@ impl Deck {
             (DrawnCard::ExploreCard(pos), explore_prob, new_deck)
         });
 
-        let total_monsters = self.monster_cards_mask.count_ones() as f32;
-        let monster_prob = m / (total_monsters * total);
+        let ruins = self.ruin_cards_mask.count_ones();
+        let ruin_iter = (ruins > 0).then(|| {
+            let mut new_deck = *self;
+            new_deck.remove_ruin_card(self.ruin_cards_mask.trailing_zeros() as u16);
+
+            (DrawnCard::Ruin, ruins as f32 / total, new_deck)
+        });
 
         let monster_mask = if self.monsters_in_deck > 0 {
             self.monster_cards_mask
         } else {
             0
         };
+        let monster_prob = self.monsters_in_deck as f32
+            / (total * self.monster_cards_mask.count_ones().max(1) as f32);
         let monster_iter = MaskIterator(monster_mask).map(move |pos| {
             let mut new_deck = *self;
             new_deck.remove_monster_card(pos);
 
-            (DrawnCard::MonsterCard(pos), m / monster_prob, new_deck)
+            (DrawnCard::MonsterCard(pos), monster_prob, new_deck)
         });
 
-        explore_iter.chain(monster_iter)
+        explore_iter.chain(ruin_iter).chain(monster_iter)
     }
 }
 
 pub enum DrawnCard {
     ExploreCard(u16),
+    Ruin,
     MonsterCard(u16),
 }
 
@@ -90,3 +139,81 @@ impl Iterator for MaskIterator {
         Some(pos)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn probabilities_sum_up_to_one() {
+        // the probabilities only depend on the number of each kind of card, so one deck of each
+        // class is enough, the seasons started are part of it as every season adds an ambush
+        let class = |deck: &Deck, seasons: u32| {
+            (
+                deck.explore_cards_mask.count_ones(),
+                deck.ruin_cards_mask.count_ones(),
+                deck.monster_cards_mask.count_ones(),
+                deck.monsters_in_deck,
+                seasons,
+            )
+        };
+
+        let mut visited = HashSet::new();
+        let mut open = vec![(Deck::empty(), 0)];
+        while let Some((deck, seasons)) = open.pop() {
+            if !visited.insert(class(&deck, seasons)) {
+                continue;
+            }
+
+            if seasons < 4 {
+                let mut next = deck;
+                next.new_season();
+                open.push((next, seasons + 1));
+            }
+
+            let cards: Vec<_> = deck.draw_cards().collect();
+            if deck.card_count() == 0 {
+                assert!(cards.is_empty());
+                continue;
+            }
+
+            let sum: f32 = cards.iter().map(|(_, prob, _)| prob).sum();
+            assert!(
+                (sum - 1.0).abs() < 1e-4,
+                "{:?} sums up to {}",
+                class(&deck, seasons),
+                sum
+            );
+            for (_, prob, next) in cards {
+                assert!(prob > 0.0 && prob <= 1.0);
+                open.push((next, seasons));
+            }
+        }
+
+        // every number of explore cards, ruins and ambushes in the deck was reached in every season
+        for seasons in 1..=4 {
+            for (explore, ruins, monsters) in (0..=11).flat_map(|e| {
+                (0..=2).flat_map(move |r| (0..=seasons as u8).map(move |m| (e, r, m)))
+            }) {
+                assert!(visited
+                    .iter()
+                    .any(|&(e, r, _, m, s)| (e, r, m, s) == (explore, ruins, monsters, seasons)));
+            }
+        }
+    }
+
+    #[test]
+    fn ambush_revealed_twice_is_removed_once() {
+        let mut deck = Deck::empty();
+        deck.new_season();
+        deck.new_season();
+        deck.remove_monster_card(3);
+        let once = (deck.monster_cards_mask, deck.monsters_in_deck);
+
+        deck.remove_monster_card(3);
+        assert_eq!((deck.monster_cards_mask, deck.monsters_in_deck), once);
+        assert_eq!(once, (0b1111_0111, 1));
+    }
+}
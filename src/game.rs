@@ -1,8 +1,10 @@
+use std::str::FromStr;
+
 use enum_map::{enum_map, Enum, EnumMap};
 
 use crate::mask::Mask;
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Enum)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Enum, Hash)]
 pub enum Season {
     Spring,
     Summer,
@@ -10,6 +12,20 @@ pub enum Season {
     Winter,
 }
 
+impl FromStr for Season {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "spring" => Ok(Self::Spring),
+            "summer" => Ok(Self::Summer),
+            "fall" | "autumn" => Ok(Self::Fall),
+            "winter" => Ok(Self::Winter),
+            _ => Err(format!("unknown season {}", s)),
+        }
+    }
+}
+
 impl Season {
     pub fn time(&self) -> u16 {
         match self {
@@ -19,6 +35,16 @@ impl Season {
         }
     }
 
+    // indices of the two degrees (A to D) which are scored at the end of the season
+    pub fn degrees(&self) -> [usize; 2] {
+        match self {
+            Self::Spring => [0, 1],
+            Self::Summer => [1, 2],
+            Self::Fall => [2, 3],
+            Self::Winter => [3, 0],
+        }
+    }
+
     pub fn next(&self) -> Option<Season> {
         match self {
             Self::Spring => Some(Self::Summer),
@@ -38,7 +64,7 @@ pub enum PlayerTerrain {
     Monster,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash)]
 pub struct PlayerBoard(EnumMap<PlayerTerrain, Mask>);
 
 impl PlayerBoard {
@@ -1,137 +1,635 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::VecDeque, net::TcpListener, process, thread, time::Duration};
 
-use search::{find_best_move, GameState, InitialState};
-use socketio::SocketIOExt;
+use ambush::AmbushModel;
+use mock::Script;
+use search::{Engine, InitialState, SearchLimits, Turn};
+use socketio::{SocketIOExt, Transport};
 use structopt::StructOpt;
-use tungstenite::http::Uri;
 
-use crate::{game::PlayerTerrain, protocol::Message};
+use crate::{
+    analysis::Position,
+    client::Client,
+    game::Season,
+    map::Map,
+    player::Player,
+    protocol::Message,
+    record::{Direction, Recorder},
+};
 
+mod ambush;
+mod analysis;
+mod bench;
 mod card;
+mod client;
 mod deck;
 mod game;
+mod map;
 mod mask;
+mod mock;
+mod monte_carlo;
+mod names;
+mod player;
+mod polling;
 mod protocol;
+mod record;
 mod scoring;
 mod search;
+mod simulation;
 mod socketio;
+mod transposition;
 
 #[derive(StructOpt)]
-struct Opt {
+enum Opt {
+    /// Play a game on a server
+    Play(PlayOpt),
+    /// Play a game offline without a server
+    Simulate(SimulateOpt),
+    /// Play many games offline and print score statistics
+    Bench(BenchOpt),
+    /// Replay a recorded game and compare the recorded turns with the turns found now
+    Replay(ReplayOpt),
+    /// Show the best turns found by the search for a position
+    Analyze(AnalyzeOpt),
+    /// Play a scripted game against a local mock server and check all turns sent by the client
+    Mock(MockOpt),
+    /// List the server ids with the german and english names of all cards
+    Names,
+}
+
+#[derive(StructOpt)]
+struct LimitsOpt {
+    /// Think time per turn in milliseconds, simulations are limited by positions instead by
+    /// default, so their results can be reproduced
+    #[structopt(long)]
+    think_time: Option<u64>,
+
+    /// Maximum search depth in cards
+    #[structopt(long)]
+    max_depth: Option<u32>,
+
+    /// Maximum number of positions evaluated per turn
+    #[structopt(long)]
+    max_positions: Option<u32>,
+
+    /// Number of search threads, all cores by default, one in simulations
+    #[structopt(long)]
+    threads: Option<usize>,
+
+    /// Search engine: expectimax, or flat monte carlo with greedy or random playouts
+    /// (monte-carlo-greedy, monte-carlo-random)
+    #[structopt(long, default_value = "expectimax")]
+    engine: Engine,
+
+    /// How the neighbors place the monsters of ambushes: worst-case or random
+    #[structopt(long, default_value = "worst-case")]
+    ambush: AmbushModel,
+}
+
+impl LimitsOpt {
+    fn limits(&self, default_think_time: u64) -> SearchLimits {
+        SearchLimits {
+            duration: Duration::from_millis(self.think_time.unwrap_or(default_think_time)),
+            max_depth: self.max_depth,
+            max_positions: self.max_positions,
+            threads: self
+                .threads
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+            engine: self.engine,
+            ambush: self.ambush,
+        }
+    }
+
+    // the same seed plays the same game: without a think time the search evaluates a fixed number
+    // of positions on one thread, which does not depend on the speed of the machine
+    fn reproducible_limits(&self, default_max_positions: u32) -> SearchLimits {
+        let (duration, max_positions) = match self.think_time {
+            Some(think_time) => {
+                println!("Think time is limited, the results depend on the speed of the machine");
+                (Duration::from_millis(think_time), self.max_positions)
+            }
+            // an hour is never reached, the positions limit the search
+            None => (
+                Duration::from_secs(60 * 60),
+                Some(self.max_positions.unwrap_or(default_max_positions)),
+            ),
+        };
+        let threads = self.threads.unwrap_or(1);
+        if threads > 1 {
+            println!(
+                "Search uses {} threads, the results depend on their timing",
+                threads
+            );
+        }
+
+        SearchLimits {
+            duration,
+            max_positions,
+            threads,
+            ..self.limits(0)
+        }
+    }
+}
+
+#[derive(StructOpt)]
+struct PlayOpt {
     #[structopt(long)]
     start: bool,
 
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// Use this map instead of the one sent by the server ("a", "b" or a map file)
+    #[structopt(long)]
+    map: Option<String>,
+
+    /// The socket.io namespace of the game
+    #[structopt(long, default_value = "/")]
+    namespace: String,
+
+    /// The engine.io transport: "websocket", "polling", or "upgrade" to start with long-polling and
+    /// switch to a websocket if possible
+    #[structopt(long, default_value = "websocket")]
+    transport: Transport,
+
+    /// A ws:// or wss:// URL, or only the host and port of the server
     #[structopt(name = "URL")]
     url: String,
 
     #[structopt(name = "NAME")]
     name: String,
+
+    /// Record all events of the game to this file (one json object per line)
+    #[structopt(long)]
+    record: Option<String>,
+}
+
+#[derive(StructOpt)]
+struct ReplayOpt {
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// Use this map instead of the one sent by the server ("a", "b" or a map file)
+    #[structopt(long)]
+    map: Option<String>,
+
+    /// A game recorded with `play --record`
+    #[structopt(name = "FILE")]
+    file: String,
+}
+
+#[derive(StructOpt)]
+struct SimulateOpt {
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// The map to play on ("a", "b" or a map file)
+    #[structopt(long, default_value = "a")]
+    map: String,
+}
+
+#[derive(StructOpt)]
+struct BenchOpt {
+    /// Number of games to play
+    #[structopt(long, default_value = "100")]
+    games: u64,
+
+    /// Seed of the first game, the following games use the next seeds
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// The map to play on ("a", "b" or a map file)
+    #[structopt(long, default_value = "a")]
+    map: String,
+}
+
+#[derive(StructOpt)]
+struct AnalyzeOpt {
+    /// The degrees A to D by id, german or english name, separated by commas
+    #[structopt(long, use_delimiter = true, required = true)]
+    degrees: Vec<String>,
+
+    /// The current season: spring, summer, fall or winter
+    #[structopt(long, default_value = "spring")]
+    season: Season,
+
+    /// Cards drawn in this season before the current card, separated by commas
+    #[structopt(long, use_delimiter = true)]
+    used: Vec<String>,
+
+    /// The season timer, by default the time of the used cards and the current card
+    #[structopt(long)]
+    timer: Option<u16>,
+
+    /// The current card has to cover a ruin
+    #[structopt(long)]
+    on_ruin: bool,
+
+    /// Number of turns to show
+    #[structopt(long, default_value = "5")]
+    top: usize,
+
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// Use this map instead of the one from the board ("a", "b" or a map file)
+    #[structopt(long)]
+    map: Option<String>,
+
+    /// The board, either json sent by the server (e.g. a recording) or a text grid
+    #[structopt(name = "BOARD")]
+    board: String,
+
+    /// The current card by id, german or english name
+    #[structopt(name = "CARD")]
+    card: String,
+}
+
+#[derive(StructOpt)]
+struct MockOpt {
+    /// Seed of the scripted game
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    #[structopt(flatten)]
+    limits: LimitsOpt,
+
+    /// The map to play on ("a", "b" or a map file)
+    #[structopt(long, default_value = "a")]
+    map: String,
+
+    /// Record all events of the game to this file (one json object per line)
+    #[structopt(long)]
+    record: Option<String>,
+
+    /// Place the ambushes on the board of a neighbor like in a game with other players
+    #[structopt(long)]
+    neighbor_ambushes: bool,
 }
 
 fn main() {
-    let opt = Opt::from_args();
+    match Opt::from_args() {
+        Opt::Play(opt) => play(opt),
+        Opt::Simulate(opt) => simulate(opt),
+        Opt::Bench(opt) => {
+            let map = Map::by_name(&opt.map).unwrap_or_else(|err| panic!("{}", err));
+            // about 100 ms per turn
+            bench::run(
+                map,
+                opt.games,
+                opt.seed,
+                &opt.limits.reproducible_limits(1_000_000),
+            );
+        }
+        Opt::Replay(opt) => replay(opt),
+        Opt::Analyze(opt) => analyze(opt),
+        Opt::Mock(opt) => mock(opt),
+        Opt::Names => {
+            for name in names::all_names() {
+                println!("{:<24} {:<24} {}", name.id, name.german, name.english);
+            }
+        }
+    }
+}
+
+fn simulate(opt: SimulateOpt) {
+    let map = Map::by_name(&opt.map).unwrap_or_else(|err| panic!("{}", err));
+    let result = simulation::play_game(
+        map,
+        opt.seed,
+        // about 2 seconds per turn
+        &opt.limits.reproducible_limits(20_000_000),
+    );
+
+    let degree_names = result.degrees.map(|d| d.name.english);
+    println!("Degrees: {}", degree_names.join(", "));
+    for (season, score) in &result.season_scores {
+        let [(a, score_a), (b, score_b)] = score.degrees;
+        println!(
+            "{:?}: {} ({}) + {} ({}) + {} (gold) + {} (monsters) = {}",
+            season,
+            score_a,
+            degree_names[a],
+            score_b,
+            degree_names[b],
+            score.gold,
+            score.monsters,
+            score.total()
+        );
+    }
+    println!("Final score: {}", result.score());
+}
 
-    let uri = Uri::builder()
-        .scheme("ws")
-        .authority(opt.url)
-        .path_and_query("/socket.io/?EIO=4&transport=websocket")
-        .build()
-        .expect("failed to parse URL");
+fn analyze(opt: AnalyzeOpt) {
+    let card_id = |name: &String| {
+        names::find(name)
+            .map(|card| card.id.to_string())
+            .unwrap_or_else(|| panic!("unknown card {}", name))
+    };
+
+    let (board, map) = analysis::load_board(&opt.board).unwrap_or_else(|err| panic!("{}", err));
+    let map = match opt.map {
+        Some(name) => Map::by_name(&name).unwrap_or_else(|err| panic!("{}", err)),
+        None => map,
+    };
+
+    let position = Position {
+        degrees: analysis::find_degrees(&opt.degrees).unwrap_or_else(|err| panic!("{}", err)),
+        board,
+        map,
+        season: opt.season,
+        used_cards: opt.used.iter().map(card_id).collect(),
+        timer: opt.timer,
+        card: card_id(&opt.card),
+        on_ruin: opt.on_ruin,
+    };
+
+    if names::is_ruin(&position.card) {
+        panic!("a ruin is not placed, use --on-ruin for the following card");
+    }
+
+    analysis::analyze(&position, opt.top, &opt.limits.limits(2000));
+}
+
+fn mock(opt: MockOpt) {
+    let map = Map::by_name(&opt.map).unwrap_or_else(|err| panic!("{}", err));
+    let listener = TcpListener::bind("127.0.0.1:0").expect("cannot start mock server");
+    let url = listener.local_addr().unwrap().to_string();
+
+    let script = Script {
+        neighbor_ambushes: opt.neighbor_ambushes,
+        ..Script::default()
+    };
+    let server = thread::spawn(move || mock::run(listener, map, opt.seed, script));
 
-    let mut socket = socketio::connect(uri).expect("cannot connect");
+    play(PlayOpt {
+        start: false,
+        limits: opt.limits,
+        map: None,
+        namespace: "/".into(),
+        transport: Transport::Websocket,
+        url,
+        name: "mock".into(),
+        record: opt.record,
+    });
 
-    socket
-        .write_event("enterGame", opt.name)
-        .expect("failed to send message");
+    let report = server
+        .join()
+        .unwrap()
+        .unwrap_or_else(|err| panic!("mock server failed: {}", err));
 
-    if opt.start {
-        socket
-            .write_event("startGame", "")
-            .expect("failed to send message");
+    for reason in &report.rejected_turns {
+        println!("Rejected turn {}", reason);
     }
+    println!(
+        "Mock game: {} turns accepted, {} rejected, score {}",
+        report.accepted_turns,
+        report.rejected_turns.len(),
+        report.score
+    );
 
-    let initial_state = loop {
-        let (event, data) = socket.read_event().expect("failed to read message");
-        if let Some(data) = data {
-            if let Some(Message::NewDegrees(degrees)) = Message::parse(&event, &data) {
-                break InitialState::new(degrees);
+    if report.aborted || !report.rejected_turns.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn play(opt: PlayOpt) {
+    let limits = opt.limits.limits(2000);
+    let map_override = opt
+        .map
+        .map(|name| Map::by_name(&name).unwrap_or_else(|err| panic!("{}", err)));
+
+    let uri = socketio::server_uri(&opt.url).unwrap_or_else(|err| panic!("{}", err));
+
+    let recorder = opt.record.map(|path| {
+        Recorder::create(&path).unwrap_or_else(|err| panic!("cannot create {}: {}", path, err))
+    });
+
+    let mut socket = Client::connect(
+        uri,
+        opt.namespace,
+        opt.transport,
+        opt.name,
+        opt.start,
+        recorder,
+    )
+    .expect("cannot connect");
+
+    let degrees = loop {
+        if let Some(Message::NewDegrees(degrees)) = read_message(&mut socket) {
+            break degrees;
+        }
+    };
+
+    // the map is only known with the first board, so keep all messages until then
+    let mut pending = VecDeque::new();
+    let map = loop {
+        if let Some(msg) = read_message(&mut socket) {
+            let map = match &msg {
+                Message::NewTurn { map, .. } => Some(*map),
+                _ => None,
+            };
+            pending.push_back(msg);
+
+            if let Some(map) = map {
+                break map;
             }
         }
     };
 
-    let mut game_state = GameState::new(&initial_state);
-    let mut card_counter = 0;
+    let initial_state = InitialState::new(degrees, map_override.unwrap_or(map));
+    let mut player = Player::new(&initial_state, limits);
+    // the player id, the sent turn and the remaining turns, to retry if the turn is rejected
+    let mut last_turn: Option<(String, Turn, VecDeque<Turn>)> = None;
 
     loop {
-        let (event, data) = socket.read_event().expect("failed to read message");
-        if let Some(data) = data {
-            let msg = Message::parse(&event, &data);
-            match msg {
-                Some(Message::NewSeason(season)) => {
-                    game_state.new_season(season);
-                    card_counter = 0;
+        let msg = pending.pop_front().or_else(|| read_message(&mut socket));
+
+        if socket.take_reconnected() {
+            player.reconnected();
+            last_turn = None;
+        }
+
+        match msg {
+            Some(Message::NewSeason(season)) => player.new_season(season),
+            Some(Message::NewTurn {
+                player_id,
+                board,
+                drawn_cards,
+                ..
+            }) => {
+                let mut candidates: VecDeque<_> =
+                    player.new_turn(&player_id, board, &drawn_cards).into();
+                let Some(turn) = candidates.pop_front() else {
+                    println!("No possible turn");
+                    continue;
+                };
+                println!("{:?}", turn);
+
+                send_turn(&mut socket, &player_id, &turn);
+                last_turn = Some((player_id, turn, candidates));
+            }
+            Some(Message::ReceivedTurn(false)) => {
+                // retry with the next best turn
+                if let Some((player_id, rejected, candidates)) = &mut last_turn {
+                    println!("Turn was rejected by the server: {:?}", rejected);
+
+                    if let Some(turn) = candidates.pop_front() {
+                        println!("Retrying with {:?}", turn);
+                        send_turn(&mut socket, player_id, &turn);
+                        *rejected = turn;
+                    } else {
+                        println!("No more possible turns");
+                    }
                 }
+            }
+            Some(Message::ReceivedTurn(true)) => {
+                last_turn = None;
+            }
+            Some(Message::FinalScoring(value)) => {
+                println!("Final scores: {}", value);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn send_turn(socket: &mut Client, player_id: &str, turn: &Turn) {
+    match socket.write_acknowledged_event("finishTurn", &protocol::turn_payload(player_id, turn)) {
+        Ok(()) => {}
+        // the connection is still intact, so the game goes on
+        Err(err @ socketio::Error::PayloadTooLarge { .. }) => {
+            println!("Turn was not sent: {}", err)
+        }
+        Err(err) => panic!("failed to send turn: {}", err),
+    }
+}
+
+fn replay(opt: ReplayOpt) {
+    let limits = opt.limits.limits(2000);
+    let entries = record::load(&opt.file).unwrap_or_else(|err| panic!("{}", err));
+
+    // the received events are parsed like in a live game
+    let messages: Vec<_> = entries
+        .iter()
+        .map(|entry| match entry.direction {
+            Direction::Received => parse_message(&entry.event, entry.data.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    let degrees = messages
+        .iter()
+        .find_map(|msg| match msg {
+            Some(Message::NewDegrees(degrees)) => Some(*degrees),
+            _ => None,
+        })
+        .expect("no degrees in recording");
+    let map = match opt.map {
+        Some(name) => Map::by_name(&name).unwrap_or_else(|err| panic!("{}", err)),
+        None => messages
+            .iter()
+            .find_map(|msg| match msg {
+                Some(Message::NewTurn { map, .. }) => Some(*map),
+                _ => None,
+            })
+            .expect("no turn in recording"),
+    };
+
+    let initial_state = InitialState::new(degrees, map);
+    let mut player = Player::new(&initial_state, limits);
+    let start_time = entries.first().map_or(0, |entry| entry.time);
+    let mut replayed_turn: Option<Turn> = None;
+
+    for (entry, msg) in entries.iter().zip(messages) {
+        let time = (entry.time - start_time) as f32 / 1000.0;
+
+        match (entry.direction, msg) {
+            (Direction::Reconnected, _) => {
+                println!("[{:.1}s] Reconnected", time);
+                player.reconnected();
+            }
+            (Direction::Received, Some(Message::NewSeason(season))) => {
+                println!("[{:.1}s] New season {:?}", time, season);
+                player.new_season(season);
+            }
+            (
+                Direction::Received,
                 Some(Message::NewTurn {
                     player_id,
                     board,
                     drawn_cards,
-                }) => {
-                    let mut on_ruin = false;
-                    let mut is_ambush = false;
-                    for c in &drawn_cards[card_counter..] {
-                        println!("Got card {}", c);
-                        if c == "tempelruinen" || c == "verfallenerAussenposten" {
-                            on_ruin = true;
-                        } else {
-                            let card = game_state.reveal_card(c);
-                            is_ambush = is_ambush || card.is_ambush();
-                        }
-                    }
-
-                    if !is_ambush {
-                        card_counter = drawn_cards.len();
-                    }
-
-                    game_state.new_board(board);
-
-                    let (turn, statistics) = find_best_move(
-                        &game_state,
-                        drawn_cards.last().unwrap(),
-                        on_ruin,
-                        Duration::from_secs(2),
-                    );
-                    println!(
-                        "Positions evaluated: {}, depth reached: {}",
-                        statistics.positions_evaluated, statistics.depth_reached
-                    );
-                    println!("{:?}", turn);
-
-                    let terrain_name = match turn.terrain {
-                        PlayerTerrain::Forest => "FOREST",
-                        PlayerTerrain::Village => "VILLAGE",
-                        PlayerTerrain::Farm => "FARM",
-                        PlayerTerrain::Water => "WATER",
-                        PlayerTerrain::Monster => "MONSTER",
-                    };
-
-                    let mut fields = HashMap::new();
-                    for pos in turn.cells.cells() {
-                        fields.insert(pos.to_string(), terrain_name.to_string());
-                    }
-
-                    socket
-                        .write_json_event(
-                            "finishTurn",
-                            &serde_json::json!({ "playerId": player_id, "fields": fields }),
-                        )
-                        .expect("failed to send turn");
+                    ..
+                }),
+            ) => {
+                replayed_turn = player
+                    .new_turn(&player_id, board, &drawn_cards)
+                    .first()
+                    .copied();
+                match replayed_turn {
+                    Some(turn) => println!("[{:.1}s] Replayed {:?}", time, turn),
+                    None => println!("[{:.1}s] No possible turn", time),
                 }
-                Some(Message::FinalScoring(value)) => {
-                    println!("Final scores: {}", value);
-                    break;
+            }
+            (Direction::Received, Some(Message::ReceivedTurn(valid))) => {
+                println!("[{:.1}s] Turn accepted by the server: {}", time, valid);
+            }
+            (Direction::Received, Some(Message::FinalScoring(value))) => {
+                println!("[{:.1}s] Final scores: {}", time, value);
+            }
+            (Direction::Sent, _) if entry.event == "finishTurn" => {
+                let recorded = entry.data.as_ref().map(protocol::parse_turn);
+                match recorded {
+                    Some(Ok((_, terrain, cells))) => {
+                        let same =
+                            replayed_turn.is_some_and(|t| t.terrain == terrain && t.cells == cells);
+                        println!(
+                            "[{:.1}s] Recorded {:?}, {}{:?}",
+                            time,
+                            terrain,
+                            if same {
+                                "same as replayed"
+                            } else {
+                                "different from replayed"
+                            },
+                            cells
+                        );
+                    }
+                    Some(Err(err)) => println!("[{:.1}s] Invalid recorded turn: {}", time, err),
+                    None => println!("[{:.1}s] Recorded turn without data", time),
                 }
-                _ => {}
             }
+            _ => {}
+        }
+    }
+}
+
+// reads the next event, a message which cannot be read is logged and skipped
+fn read_message(socket: &mut Client) -> Option<Message> {
+    match socket.read_event() {
+        Ok((event, data)) => parse_message(&event, data.as_ref()),
+        // the connection could not be reestablished, so every further read fails as well
+        Err(err) if socket.is_closed() => panic!("connection lost: {}", err),
+        Err(err) => {
+            println!("Skipping message: {}", err);
+            None
+        }
+    }
+}
+
+// events which cannot be parsed are logged and skipped
+fn parse_message(event: &str, data: Option<&serde_json::Value>) -> Option<Message> {
+    match Message::parse(event, data?) {
+        Ok(msg) => msg,
+        Err(err) => {
+            println!("Skipping event {}: {}", event, err);
+            None
         }
     }
 }
@@ -0,0 +1,315 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use serde_json::Value;
+
+use crate::mask::Mask;
+
+// the static terrain of a map sheet, which is already printed before the game starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Map {
+    pub mountain: Mask,
+    pub ruin: Mask,
+    pub wasteland: Mask,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapTerrain {
+    Mountain,
+    Ruin,
+    Wasteland,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Format(String),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read map: {}", err),
+            Self::Json(err) => write!(f, "invalid map json: {}", err),
+            Self::Format(msg) => write!(f, "invalid map: {}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl Map {
+    pub fn new_with(mut f: impl FnMut(u8) -> Option<MapTerrain>) -> Self {
+        let mut result = Self {
+            mountain: Mask::empty(),
+            ruin: Mask::empty(),
+            wasteland: Mask::empty(),
+        };
+
+        for idx in 0..Mask::CELL_COUNT {
+            let cell = Mask::cell_idx(idx);
+            match f(idx) {
+                Some(MapTerrain::Mountain) => result.mountain |= cell,
+                Some(MapTerrain::Ruin) => result.ruin |= cell,
+                Some(MapTerrain::Wasteland) => result.wasteland |= cell,
+                None => {}
+            }
+        }
+
+        result
+    }
+
+    // side A of the map sheet (mountains and ruins only)
+    pub const fn side_a() -> Self {
+        Self {
+            mountain: Mask::from_cells(&[14, 30, 60, 90, 106]),
+            ruin: Mask::from_cells(&[16, 23, 31, 89, 97, 104]),
+            wasteland: Mask::empty(),
+        }
+    }
+
+    // side B of the map sheet (mountains, ruins and wasteland)
+    pub const fn side_b() -> Self {
+        Self {
+            mountain: Mask::from_cells(&[19, 24, 71, 86, 102]),
+            ruin: Mask::from_cells(&[15, 42, 51, 56, 79, 94]),
+            wasteland: Mask::from_cells(&[28, 29, 47, 48, 59, 106, 107, 118]),
+        }
+    }
+
+    // either "a" or "b" for a built-in map, or a path to a map file
+    pub fn by_name(name: &str) -> Result<Self> {
+        match name {
+            "a" | "A" => Ok(Self::side_a()),
+            "b" | "B" => Ok(Self::side_b()),
+            path => Self::load(path),
+        }
+    }
+
+    // a map file is either a text grid or json, see `from_grid` and `from_json`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let trimmed = content.trim_start();
+
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            Self::from_json(&serde_json::from_str(trimmed)?)
+        } else {
+            Self::from_grid(&grid_rows(&content))
+        }
+    }
+
+    // one row per line, with 'm' for mountains, 'r' for ruins, 'w' for wasteland and '.' or ' ' for
+    // empty cells, e.g.
+    // ...........
+    // ...m.r.....
+    // .r......mr.
+    pub fn from_grid(rows: &[&str]) -> Result<Self> {
+        if rows.len() != Mask::SIZE as usize {
+            return Err(Error::Format(format!(
+                "expected {} rows, got {}",
+                Mask::SIZE,
+                rows.len()
+            )));
+        }
+
+        let mut result = Self::new_with(|_| None);
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() > Mask::SIZE as usize {
+                return Err(Error::Format(format!("row {} is too long", y + 1)));
+            }
+
+            for (x, c) in row.chars().enumerate() {
+                let cell = Mask::cell(x as u8, y as u8);
+                match c.to_ascii_lowercase() {
+                    'm' => result.mountain |= cell,
+                    'r' => result.ruin |= cell,
+                    'w' => result.wasteland |= cell,
+                    '.' | ' ' => {}
+                    c => {
+                        return Err(Error::Format(format!(
+                            "unknown terrain '{}' in row {}",
+                            c,
+                            y + 1
+                        )))
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    // either an array of rows as in `from_grid`, or an object with lists of cell indices, e.g.
+    // {"mountain": [14, 30, 60, 90, 106], "ruin": [16, 23, 31, 89, 97, 104], "wasteland": []}
+    pub fn from_json(value: &Value) -> Result<Self> {
+        match value {
+            Value::Array(rows) => {
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        row.as_str()
+                            .ok_or_else(|| Error::Format("expected a row string".into()))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Self::from_grid(&rows)
+            }
+            Value::Object(cells) => {
+                let mask = |key: &str| -> Result<Mask> {
+                    let mut mask = Mask::empty();
+                    if let Some(cells) = cells.get(key) {
+                        let cells = cells
+                            .as_array()
+                            .ok_or_else(|| Error::Format(format!("expected a list of {}", key)))?;
+                        for cell in cells {
+                            match cell.as_u64() {
+                                Some(idx) if idx < Mask::CELL_COUNT as u64 => {
+                                    mask |= Mask::cell_idx(idx as u8)
+                                }
+                                _ => {
+                                    return Err(Error::Format(format!(
+                                        "invalid {} cell {}",
+                                        key, cell
+                                    )))
+                                }
+                            }
+                        }
+                    }
+                    Ok(mask)
+                };
+
+                Ok(Self {
+                    mountain: mask("mountain")?,
+                    ruin: mask("ruin")?,
+                    wasteland: mask("wasteland")?,
+                })
+            }
+            _ => Err(Error::Format("expected an array or an object".into())),
+        }
+    }
+}
+
+// the rows of a text grid file, a row of spaces is an empty row, only empty lines at the end are
+// not part of the grid
+pub fn grid_rows(content: &str) -> Vec<&str> {
+    let mut rows: Vec<_> = content.lines().collect();
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    rows.into_iter().map(str::trim_end).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    const SIDE_A: [&str; 11] = [
+        "...........",
+        "...m.r.....",
+        ".r......mr.",
+        "...........",
+        "...........",
+        ".....m.....",
+        "...........",
+        "...........",
+        ".rm......r.",
+        ".....r.m...",
+        "...........",
+    ];
+
+    const SIDE_B: [&str; 11] = [
+        "...........",
+        "....r...m..",
+        "..m...ww...",
+        ".........r.",
+        "...ww..r...",
+        ".r..w......",
+        ".....m.....",
+        "..r......m.",
+        "......r....",
+        "...m...ww..",
+        "........w..",
+    ];
+
+    fn to_grid(map: &Map) -> Vec<String> {
+        (0..Mask::SIZE)
+            .map(|y| {
+                (0..Mask::SIZE)
+                    .map(|x| {
+                        let cell = Mask::cell(x, y);
+                        if map.mountain.contains(cell) {
+                            'm'
+                        } else if map.ruin.contains(cell) {
+                            'r'
+                        } else if map.wasteland.contains(cell) {
+                            'w'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn side_a_matches_the_former_masks() {
+        let map = Map::side_a();
+        assert_eq!(map.mountain, Mask::from_cells(&[14, 30, 60, 90, 106]));
+        assert_eq!(map.ruin, Mask::from_cells(&[16, 23, 31, 89, 97, 104]));
+        assert!(map.wasteland.is_empty());
+
+        assert_eq!(Map::from_grid(&SIDE_A).unwrap(), map);
+    }
+
+    #[test]
+    fn side_b_matches_the_grid() {
+        assert_eq!(Map::from_grid(&SIDE_B).unwrap(), Map::side_b());
+        assert_eq!(to_grid(&Map::side_b()), SIDE_B);
+    }
+
+    #[test]
+    fn user_map_round_trip() {
+        let map = Map {
+            mountain: Mask::from_cells(&[0, 60, 120]),
+            ruin: Mask::from_cells(&[12, 108]),
+            wasteland: Mask::from_cells(&[55, 56, 57]),
+        };
+
+        // empty cells are written as spaces, so some rows consist of spaces only
+        let rows: Vec<_> = to_grid(&map).iter().map(|r| r.replace('.', " ")).collect();
+        let content = rows.join("\n") + "\n\n";
+
+        let path = env::temp_dir().join(format!("cartographers-map-{}.txt", std::process::id()));
+        fs::write(&path, content).unwrap();
+        let loaded = Map::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), map);
+    }
+
+    #[test]
+    fn row_of_spaces_is_not_skipped() {
+        let path = env::temp_dir().join(format!("cartographers-spaces-{}.txt", std::process::id()));
+        let mut rows = SIDE_A.map(str::to_string);
+        rows[0] = " ".repeat(11);
+        fs::write(&path, rows.join("\n")).unwrap();
+        let loaded = Map::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), Map::side_a());
+    }
+}
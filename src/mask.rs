@@ -25,7 +25,7 @@ const fn column(i: u8) -> u128 {
     column << i
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[must_use]
 pub struct Mask(u128);
 
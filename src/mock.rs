@@ -0,0 +1,537 @@
+use std::net::{TcpListener, TcpStream};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde_json::{json, Value};
+use tungstenite::WebSocket;
+
+use crate::{
+    card::{explore_cards, monster_cards, splitterland, splitterland_monster, ExploreCard},
+    game::{PlayerTerrain, Season},
+    map::Map,
+    mask::Mask,
+    names, protocol,
+    scoring::{Board, Score},
+    search::{for_each_turn, GameState, InitialState, Turn},
+    socketio::{Error, Result, SocketIOExt, SocketIOPacket},
+};
+
+const PLAYER_ID: &str = "mock-player";
+const NEIGHBOR_ID: &str = "mock-neighbor";
+// the game is aborted if the client sends this many invalid turns for the same card
+const MAX_REJECTED_TURNS: usize = 3;
+
+// deviations from a regular game, to test how the client handles them
+#[derive(Clone, Copy, Default)]
+pub struct Script {
+    // the first turn is rejected even if it is valid
+    pub reject_first_turn: bool,
+    // the client places the ambushes on the board of a neighbor like in a game with other players,
+    // its board is different from ours
+    pub neighbor_ambushes: bool,
+}
+
+// the outcome of a mock game
+pub struct Report {
+    // the newTurn events sent, and how many of them were for an ambush or for the board of the
+    // neighbor
+    pub turns: u32,
+    pub ambush_turns: u32,
+    pub neighbor_turns: u32,
+    pub accepted_turns: u32,
+    // the reasons why turns were rejected
+    pub rejected_turns: Vec<String>,
+    pub aborted: bool,
+    pub score: Score,
+}
+
+// a client connection on the server side, in the namespace requested by the client
+struct Connection {
+    socket: WebSocket<TcpStream>,
+    namespace: String,
+}
+
+impl SocketIOExt for Connection {
+    fn read_raw_message(&mut self) -> Result<String> {
+        self.socket.read_raw_message()
+    }
+
+    fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+        self.socket.write_raw_message(msg)
+    }
+
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        self.socket.read_raw_binary()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+}
+
+// accepts a single client and plays a scripted game with it like the real server, all cards are
+// shuffled by the seed and every turn sent by the client is checked against the rules
+pub fn run(listener: TcpListener, map: Map, seed: u64, script: Script) -> Result<Report> {
+    let (stream, _) = listener.accept()?;
+    let socket = tungstenite::accept(stream)
+        .map_err(|err| Error::EngineIO(format!("websocket handshake failed: {}", err)))?;
+    let mut connection = connect(socket)?;
+
+    connection.expect_event("enterGame")?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut categories = names::DEGREES;
+    categories.shuffle(&mut rng);
+    let degrees = categories.map(|degrees| *degrees.choose(&mut rng).unwrap());
+    connection.write_json_event(
+        "newDegrees",
+        &degrees
+            .iter()
+            .map(|d| json!({ "card": d.name.id }))
+            .collect(),
+    )?;
+
+    let initial_state = InitialState::new(degrees.map(|d| d.scoring), map);
+    let mut state = GameState::new(&initial_state);
+    let mut neighbor = GameState::new(&initial_state);
+    // every other row of the neighbor is a forest, so its empty cells are not ours
+    let rows = (0..Mask::CELL_COUNT).filter(|idx| (idx / Mask::SIZE).is_multiple_of(2));
+    neighbor.apply_turn(&Turn {
+        terrain: PlayerTerrain::Forest,
+        cells: Mask::from_cells(&rows.collect::<Vec<_>>()) & neighbor.empty(),
+        gold: 0,
+    });
+    let cards: Vec<_> = explore_cards().into_iter().chain(monster_cards()).collect();
+
+    let mut ambush_pile: Vec<_> = monster_cards()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    ambush_pile.shuffle(&mut rng);
+    let mut ambushes_in_deck = Vec::new();
+
+    let mut report = Report {
+        turns: 0,
+        ambush_turns: 0,
+        neighbor_turns: 0,
+        accepted_turns: 0,
+        rejected_turns: Vec::new(),
+        aborted: false,
+        score: 0,
+    };
+    let mut next_season = Some(Season::Spring);
+
+    while let Some(season) = next_season {
+        state.new_season(season);
+        connection.write_json_event("newSeason", &json!({ "name": season_name(season) }))?;
+        ambushes_in_deck.extend(ambush_pile.pop());
+
+        let mut deck: Vec<_> = explore_cards()
+            .iter()
+            .map(|c| c.name().to_string())
+            .chain(names::RUIN_CARDS.iter().map(|c| c.id.to_string()))
+            .chain(ambushes_in_deck.iter().cloned())
+            .collect();
+        deck.shuffle(&mut rng);
+
+        let mut used_cards: Vec<String> = Vec::new();
+        let mut on_ruin = false;
+        while state.season_timer() < season.time() {
+            let name = deck.pop().expect("deck ran out of cards");
+            if names::is_ruin(&name) {
+                state.reveal_card(&name).expect("card of the deck");
+                on_ruin = true;
+                used_cards.push(name);
+                continue;
+            }
+
+            let card = cards.iter().find(|c| c.name() == name).unwrap();
+            state.reveal_card(&name).expect("card of the deck");
+
+            let for_neighbor = card.is_ambush() && script.neighbor_ambushes;
+            let (player_id, board) = if for_neighbor {
+                (NEIGHBOR_ID, &mut neighbor)
+            } else {
+                (PLAYER_ID, &mut state)
+            };
+            connection.write_json_event(
+                "newTurn",
+                &json!({
+                    "playerId": player_id,
+                    "fields": board_fields(board),
+                    "usedCards": used_cards.iter().map(|c| json!({ "name": c })).collect::<Vec<_>>(),
+                    "exploreCard": { "name": name },
+                }),
+            )?;
+
+            report.turns += 1;
+            if card.is_ambush() {
+                report.ambush_turns += 1;
+            }
+            if for_neighbor {
+                report.neighbor_turns += 1;
+            }
+
+            let turn = match receive_turn(
+                &mut connection,
+                player_id,
+                board,
+                card,
+                on_ruin,
+                script,
+                &mut report,
+            )? {
+                Some(turn) => turn,
+                None => {
+                    report.aborted = true;
+                    connection.write_json_event(
+                        "finalScoring",
+                        &json!({ "aborted": "too many invalid turns" }),
+                    )?;
+                    return Ok(report);
+                }
+            };
+            board.apply_turn(&turn);
+
+            // our other neighbor places the monster on our board, anywhere it fits
+            if for_neighbor {
+                let mut placed = None;
+                for_each_turn(&state, card, |_, state| {
+                    placed.get_or_insert(state);
+                });
+                state = placed.unwrap_or(state);
+            }
+
+            // like the real server, an ambush is not part of the used cards
+            if card.is_ambush() {
+                ambushes_in_deck.retain(|c| *c != name);
+            } else {
+                on_ruin = false;
+                used_cards.push(name);
+            }
+        }
+
+        report.score += state.season_score().total();
+        next_season = season.next();
+    }
+
+    connection.write_json_event(
+        "finalScoring",
+        &json!([{ "playerId": PLAYER_ID, "score": report.score }]),
+    )?;
+
+    Ok(report)
+}
+
+// the engine.io and socket.io handshake of the server side
+fn connect(socket: WebSocket<TcpStream>) -> Result<Connection> {
+    let mut connection = Connection {
+        socket,
+        namespace: "/".into(),
+    };
+
+    let handshake = json!({
+        "sid": "mock",
+        "upgrades": [],
+        "pingInterval": 25000,
+        "pingTimeout": 20000,
+        "maxPayload": 1000000,
+    });
+    connection.write_engineio_packet(format!("0{}", handshake))?;
+
+    let packet = connection.read_socketio_packet()?;
+    if packet.kind != '0' {
+        return Err(Error::SocketIO(format!(
+            "expected connect package, instead got {:?}",
+            packet
+        )));
+    }
+
+    connection.namespace = packet.namespace;
+    let namespace = connection.namespace.clone();
+    connection.write_socketio_packet(&SocketIOPacket::new(
+        '0',
+        &namespace,
+        None,
+        json!({ "sid": "mock" }).to_string(),
+    ))?;
+
+    Ok(connection)
+}
+
+// waits for a valid turn, returns None if the client sent too many invalid turns
+fn receive_turn(
+    connection: &mut Connection,
+    player_id: &str,
+    state: &GameState,
+    card: &ExploreCard,
+    on_ruin: bool,
+    script: Script,
+    report: &mut Report,
+) -> Result<Option<Turn>> {
+    for _ in 0..MAX_REJECTED_TURNS {
+        let data = loop {
+            let (event, data) = connection.read_event()?;
+            match event.as_str() {
+                "finishTurn" => break data.unwrap_or(Value::Null),
+                // only relevant for the real server
+                "startGame" => {}
+                _ => return Err(Error::SocketIO(format!("unexpected event {}", event))),
+            }
+        };
+
+        let first_turn = report.accepted_turns == 0 && report.rejected_turns.is_empty();
+        let result = if script.reject_first_turn && first_turn {
+            Err("rejected by the script".into())
+        } else {
+            check_turn(player_id, state, card, on_ruin, &data)
+        };
+
+        match result {
+            Ok(turn) => {
+                report.accepted_turns += 1;
+                connection.write_json_event("receivedTurn", &true.into())?;
+                return Ok(Some(turn));
+            }
+            Err(reason) => {
+                report
+                    .rejected_turns
+                    .push(format!("{}: {} ({})", card.name(), reason, data));
+                connection.write_json_event("receivedTurn", &false.into())?;
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// checks a finishTurn payload against the rules and returns the turn it describes
+fn check_turn(
+    expected_player_id: &str,
+    state: &GameState,
+    card: &ExploreCard,
+    on_ruin: bool,
+    data: &Value,
+) -> std::result::Result<Turn, String> {
+    let (player_id, terrain, cells) = protocol::parse_turn(data).map_err(|err| err.to_string())?;
+    if player_id != expected_player_id {
+        return Err(format!("unknown player id {}", player_id));
+    }
+
+    let empty = state.empty();
+    if !empty.contains(cells) {
+        return Err("cells are not empty".into());
+    }
+
+    // an ambush does not have to cover the ruin
+    let on_ruin = on_ruin && !card.is_ambush();
+    let is_possible = |cells: Mask| !on_ruin || (cells & state.ruin()).has_cells();
+
+    // if the card cannot be placed at all, a single cell of any terrain is placed instead
+    let rift_land;
+    let card = if card
+        .patterns()
+        .iter()
+        .any(|&(pattern, _)| empty.sub_masks(pattern).any(is_possible))
+    {
+        if !is_possible(cells) {
+            return Err("cells do not cover a ruin".into());
+        }
+        card
+    } else {
+        rift_land = if card.is_ambush() {
+            splitterland_monster()
+        } else {
+            splitterland()
+        };
+        &rift_land
+    };
+
+    if !card.terrains().contains(&terrain) {
+        return Err(format!("{:?} is not a terrain of the card", terrain));
+    }
+
+    let gold = card
+        .patterns()
+        .iter()
+        .find(|&&(pattern, _)| Mask::full().sub_masks(pattern).any(|m| m == cells))
+        .map(|&(_, gold)| gold)
+        .ok_or("cells do not match a pattern of the card")?;
+
+    Ok(Turn {
+        terrain,
+        cells,
+        gold,
+    })
+}
+
+// the board in the format of the server, covered ruins are not visible
+fn board_fields(state: &GameState) -> Value {
+    let terrains = [
+        (state.forest(), "FOREST"),
+        (state.village(), "VILLAGE"),
+        (state.farm(), "FARM"),
+        (state.water(), "WATER"),
+        (state.monster(), "MONSTER"),
+        (state.mountain(), "MOUNTAIN"),
+        (state.wasteland(), "WASTELAND"),
+        (state.ruin(), "Ruin"),
+    ];
+
+    (0..Mask::CELL_COUNT)
+        .map(|idx| {
+            let landscape = terrains
+                .iter()
+                .find(|(mask, _)| mask.contains(Mask::cell_idx(idx)))
+                .map_or("EMPTY", |&(_, name)| name);
+            json!({ "landscape": landscape })
+        })
+        .collect()
+}
+
+// the names used by the server
+fn season_name(season: Season) -> &'static str {
+    match season {
+        Season::Spring => "spring",
+        Season::Summer => "sommer",
+        Season::Fall => "autmn",
+        Season::Winter => "winter",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use structopt::StructOpt;
+
+    use super::*;
+    use crate::{socketio::Transport, LimitsOpt, PlayOpt};
+
+    // plays a whole game with the client of the bot against the mock server
+    fn play_mock_game(seed: u64, namespace: &str, script: Script) -> Report {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || run(listener, Map::side_a(), seed, script));
+
+        crate::play(PlayOpt {
+            start: false,
+            limits: LimitsOpt::from_iter(["mock", "--max-depth", "0", "--threads", "1"]),
+            map: None,
+            namespace: namespace.into(),
+            transport: Transport::Websocket,
+            url,
+            name: "test".into(),
+            record: None,
+        });
+
+        server.join().unwrap().unwrap()
+    }
+
+    #[test]
+    fn every_turn_is_accepted() {
+        let report = play_mock_game(0, "/", Script::default());
+
+        assert!(!report.aborted);
+        assert_eq!(report.rejected_turns, Vec::<String>::new());
+        assert!(report.turns > 0);
+        assert_eq!(report.accepted_turns, report.turns);
+    }
+
+    #[test]
+    fn rejected_turn_is_sent_again() {
+        let script = Script {
+            reject_first_turn: true,
+            ..Script::default()
+        };
+        let report = play_mock_game(1, "/game", script);
+
+        assert!(!report.aborted);
+        assert_eq!(report.rejected_turns.len(), 1);
+        assert!(report.rejected_turns[0].contains("rejected by the script"));
+        assert_eq!(report.accepted_turns, report.turns);
+    }
+
+    #[test]
+    fn ambush_turns_are_accepted() {
+        let report = play_mock_game(2, "/", Script::default());
+
+        assert!(report.ambush_turns > 0);
+        assert_eq!(report.rejected_turns, Vec::<String>::new());
+        assert_eq!(report.accepted_turns, report.turns);
+    }
+
+    #[test]
+    fn neighbor_ambushes_are_placed_on_their_board() {
+        let script = Script {
+            neighbor_ambushes: true,
+            ..Script::default()
+        };
+        let report = play_mock_game(2, "/", script);
+
+        assert!(report.neighbor_turns > 0);
+        assert_eq!(report.rejected_turns, Vec::<String>::new());
+        assert_eq!(report.accepted_turns, report.turns);
+    }
+
+    #[test]
+    fn invalid_turns_are_rejected() {
+        let initial_state = InitialState::new(names::DEGREES.map(|d| d[0].scoring), Map::side_a());
+        let mut state = GameState::new(&initial_state);
+        state.new_season(Season::Spring);
+        let card = explore_cards()
+            .into_iter()
+            .find(|c| c.name() == "weiler")
+            .unwrap();
+
+        let turn = |player_id: &str, terrain: &str, cells: &[u8]| {
+            let fields: serde_json::Map<_, _> = cells
+                .iter()
+                .map(|idx| (idx.to_string(), json!(terrain)))
+                .collect();
+            json!({ "playerId": player_id, "fields": fields })
+        };
+
+        let valid = check_turn(
+            PLAYER_ID,
+            &state,
+            &card,
+            false,
+            &turn(PLAYER_ID, "VILLAGE", &[0, 1, 11]),
+        );
+        assert_eq!(valid.map(|t| t.terrain), Ok(PlayerTerrain::Village));
+
+        let invalid = [
+            (turn("someone", "VILLAGE", &[0, 1, 11]), "unknown player id"),
+            (
+                turn(PLAYER_ID, "FOREST", &[0, 1, 11]),
+                "not a terrain of the card",
+            ),
+            (
+                turn(PLAYER_ID, "VILLAGE", &[0, 1, 2, 3, 4]),
+                "do not match a pattern",
+            ),
+            // a mountain of side a
+            (turn(PLAYER_ID, "VILLAGE", &[14, 15, 25]), "not empty"),
+        ];
+        for (data, reason) in invalid {
+            let err = check_turn(PLAYER_ID, &state, &card, false, &data)
+                .map(|t| t.cells)
+                .unwrap_err();
+            assert!(err.contains(reason), "{} instead of {}", err, reason);
+        }
+
+        // on a ruin, the turn has to cover one
+        let not_on_ruin = check_turn(
+            PLAYER_ID,
+            &state,
+            &card,
+            true,
+            &turn(PLAYER_ID, "VILLAGE", &[0, 1, 11]),
+        );
+        assert_eq!(
+            not_on_ruin.map(|t| t.cells).unwrap_err(),
+            "cells do not cover a ruin"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::Instant,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    ambush::AmbushModel,
+    search::{for_each_turn, GameState, Rollout, SearchLimits, Statistics, Turn},
+};
+
+// weight of the exploration in ucb1, in points of the final score
+const EXPLORATION: f32 = 10.0;
+
+// the played out games after a turn of the root
+#[derive(Clone, Copy, Default)]
+struct Node {
+    visits: u32,
+    score_sum: f32,
+}
+
+impl Node {
+    fn mean_score(&self) -> f32 {
+        self.score_sum / self.visits.max(1) as f32
+    }
+}
+
+// flat monte carlo: plays out games after the turns until one of the limits is reached, the turns
+// are chosen by ucb1 but no tree is grown below them, every thread plays its own games, the score
+// of a turn is its mean final score, none if a limit was reached before every turn was played out
+pub fn search(
+    turns: &[(Turn, GameState)],
+    is_ambush: bool,
+    rollout: Rollout,
+    limits: &SearchLimits,
+    seed: u64,
+) -> Option<(Vec<(Turn, f32)>, Statistics)> {
+    let deadline = Instant::now() + limits.duration;
+    let max_positions = limits.max_positions.unwrap_or(u32::MAX);
+    let positions_evaluated = AtomicU32::new(0);
+    let longest_game = AtomicU32::new(0);
+
+    let mut nodes = vec![Node::default(); turns.len()];
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..limits.threads.max(1))
+            .map(|thread_idx| {
+                let positions_evaluated = &positions_evaluated;
+                let longest_game = &longest_game;
+                scope.spawn(move || {
+                    // every thread plays other games, and so does every search
+                    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(thread_idx as u64));
+                    let mut nodes = vec![Node::default(); turns.len()];
+
+                    for total_visits in 0.. {
+                        if positions_evaluated.load(Ordering::Relaxed) >= max_positions
+                            || Instant::now() >= deadline
+                        {
+                            break;
+                        }
+
+                        let idx = select(&nodes, total_visits, is_ambush);
+                        let mut state = turns[idx].1;
+                        let (score, cards) = play_out(&mut state, rollout, limits.ambush, &mut rng);
+
+                        positions_evaluated.fetch_add(cards, Ordering::Relaxed);
+                        longest_game.fetch_max(cards, Ordering::Relaxed);
+                        nodes[idx].visits += 1;
+                        nodes[idx].score_sum += score;
+                    }
+
+                    nodes
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (node, worker_node) in nodes.iter_mut().zip(worker.join().unwrap()) {
+                node.visits += worker_node.visits;
+                node.score_sum += worker_node.score_sum;
+            }
+        }
+    });
+
+    if nodes.iter().any(|node| node.visits == 0) {
+        return None;
+    }
+
+    let ranking = turns
+        .iter()
+        .zip(&nodes)
+        .map(|(&(turn, _), node)| (turn, node.mean_score()))
+        .collect();
+
+    let statistics = Statistics {
+        positions_evaluated: positions_evaluated.into_inner(),
+        depth_reached: longest_game.into_inner(),
+        end_reached: true,
+    };
+
+    Some((ranking, statistics))
+}
+
+// the turn with the highest upper confidence bound, unvisited turns first
+fn select(nodes: &[Node], total_visits: usize, is_ambush: bool) -> usize {
+    if let Some(idx) = nodes.iter().position(|node| node.visits == 0) {
+        return idx;
+    }
+
+    let log_visits = (total_visits as f32).ln();
+    let ucb = |node: &Node| {
+        // an ambush is placed to minimize our score
+        let score = if is_ambush {
+            -node.mean_score()
+        } else {
+            node.mean_score()
+        };
+        score + EXPLORATION * (log_visits / node.visits as f32).sqrt()
+    };
+
+    (0..nodes.len())
+        .max_by(|&a, &b| ucb(&nodes[a]).total_cmp(&ucb(&nodes[b])))
+        .unwrap()
+}
+
+// plays until the end of the game, returns the final score and the number of drawn cards
+fn play_out(
+    state: &mut GameState,
+    rollout: Rollout,
+    ambush: AmbushModel,
+    rng: &mut StdRng,
+) -> (f32, u32) {
+    let mut cards = 0;
+
+    while state.handle_season_end() {
+        let mut drawn: f32 = rng.gen();
+        let (card, next_state) = state
+            .draw_cards()
+            .find(|&(_, prob, _)| {
+                drawn -= prob;
+                drawn < 0.0
+            })
+            .or_else(|| state.draw_cards().last())
+            .map(|(card, _, next_state)| (card, next_state))
+            .expect("no card left in the deck");
+
+        // nothing is placed for a ruin, the next card is drawn right away
+        let Some(card) = card else {
+            *state = next_state;
+            continue;
+        };
+
+        let is_ambush = card.is_ambush();
+        let rollout = match ambush {
+            _ if !is_ambush => rollout,
+            AmbushModel::WorstCase => Rollout::Greedy,
+            AmbushModel::Random => Rollout::Random,
+        };
+
+        let mut chosen: Option<(f32, GameState)> = None;
+        let mut turn_count = 0;
+
+        for_each_turn(&next_state, card, |_, state| match rollout {
+            Rollout::Random => {
+                // reservoir sampling, every turn is chosen with the same probability
+                turn_count += 1;
+                if rng.gen_range(0..turn_count) == 0 {
+                    chosen = Some((0.0, state));
+                }
+            }
+            Rollout::Greedy => {
+                let score = state.heuristic_score();
+                let is_better = chosen.is_none_or(|(best_score, _)| {
+                    if is_ambush {
+                        score < best_score
+                    } else {
+                        score > best_score
+                    }
+                });
+                if is_better {
+                    chosen = Some((score, state));
+                }
+            }
+        });
+
+        // without a possible turn nothing is placed
+        *state = chosen.map_or(next_state, |(_, state)| state);
+        cards += 1;
+    }
+
+    (state.final_score(), cards)
+}
@@ -0,0 +1,227 @@
+use crate::scoring::{self, Scoring};
+
+// the names of a card: the id used by the server, and the names printed on the german and english cards
+#[derive(Debug, Clone, Copy)]
+pub struct CardName {
+    pub id: &'static str,
+    pub german: &'static str,
+    pub english: &'static str,
+}
+
+const fn name(id: &'static str, german: &'static str, english: &'static str) -> CardName {
+    CardName {
+        id,
+        german,
+        english,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Degree {
+    pub name: CardName,
+    pub scoring: Scoring,
+}
+
+const fn degree(name: CardName, scoring: Scoring) -> Degree {
+    Degree { name, scoring }
+}
+
+pub const EXPLORE_CARDS: [CardName; 11] = [
+    name("ackerland", "Ackerland", "Farmland"),
+    name("baumwipfeldorf", "Baumwipfeldorf", "Treetop Village"),
+    name("fischerdorf", "Fischerdorf", "Fishing Village"),
+    name("gehoeft", "Gehöft", "Homestead"),
+    name("grosserStrom", "Großer Strom", "Great River"),
+    name("hinterlandbach", "Hinterlandbach", "Hinterland Stream"),
+    name("obsthain", "Obsthain", "Orchard"),
+    name("splitterland", "Splitterland", "Rift Lands"),
+    name("sumpf", "Sumpf", "Marshlands"),
+    name("vergessenerWald", "Vergessener Wald", "Forgotten Forest"),
+    name("weiler", "Weiler", "Hamlet"),
+];
+
+pub const MONSTER_CARDS: [CardName; 8] = [
+    name("gnollangriff", "Gnollangriff", "Gnoll Raid"),
+    name("goblinattacke", "Goblinattacke", "Goblin Attack"),
+    name(
+        "grottenschratueberfall",
+        "Grottenschrat-Überfall",
+        "Bugbear Assault",
+    ),
+    name(
+        "insektoideninvasion",
+        "Insektoideninvasion",
+        "Insectoid Invasion",
+    ),
+    name("koboldansturm", "Koboldansturm", "Kobold Onslaught"),
+    name("ogeroffensive", "Ogeroffensive", "Ogre Offensive"),
+    name(
+        "rattenmenschenrache",
+        "Rattenmenschenrache",
+        "Ratfolk Revenge",
+    ),
+    name("schindersturm", "Schindersturm", "Flayer Storm"),
+];
+
+pub const RUIN_CARDS: [CardName; 2] = [
+    name("tempelruinen", "Tempelruinen", "Temple Ruins"),
+    name(
+        "verfallenerAussenposten",
+        "Verfallener Außenposten",
+        "Outpost Ruins",
+    ),
+];
+
+// all degrees grouped by category (forest, farm and water, village, distance)
+pub const DEGREES: [[Degree; 4]; 4] = [
+    [
+        degree(
+            name("wald1", "Steinwald", "Stoneside Forest"),
+            scoring::stoneside_forest,
+        ),
+        degree(
+            name("wald2", "Wächterwald", "Sentinel Wood"),
+            scoring::sentinel_wood,
+        ),
+        degree(name("wald3", "Baumturm", "Treetower"), scoring::treetower),
+        degree(
+            name("wald4", "Grünzweig", "Greenbough"),
+            scoring::greenbough,
+        ),
+    ],
+    [
+        degree(
+            name("wasser1", "Magiertal", "Mage Valley"),
+            scoring::mage_valley,
+        ),
+        degree(
+            name("wasser2", "Kanalsee", "Canal Lake"),
+            scoring::canal_lake,
+        ),
+        degree(
+            name("wasser3", "Uferweiten", "Shoreside Expanse"),
+            scoring::shoreside_expanse,
+        ),
+        degree(
+            name("wasser4", "Die goldene Kornkammer", "The Golden Granary"),
+            scoring::the_golden_granary,
+        ),
+    ],
+    [
+        degree(
+            name("dorf1", "Grüngoldene Ebenen", "Greengold Plains"),
+            scoring::greengold_plains,
+        ),
+        degree(
+            name("dorf2", "Schildtor", "Shieldgate"),
+            scoring::shieldgate,
+        ),
+        degree(name("dorf3", "Wildfesten", "Wildholds"), scoring::wildholds),
+        degree(
+            name("dorf4", "Große Stadt", "Great City"),
+            scoring::great_city,
+        ),
+    ],
+    [
+        degree(
+            name("distanz1", "Grenzlande", "Borderlands"),
+            scoring::borderlands,
+        ),
+        degree(
+            name("distanz2", "Die Kessel", "The Cauldrons"),
+            scoring::the_cauldrons,
+        ),
+        degree(
+            name("distanz3", "Die zerbrochene Straße", "The Broken Road"),
+            scoring::the_broken_road,
+        ),
+        degree(
+            name("distanz4", "Verlorene Baronie", "Lost Barony"),
+            scoring::lost_barony,
+        ),
+    ],
+];
+
+pub fn all_names() -> impl Iterator<Item = &'static CardName> {
+    EXPLORE_CARDS
+        .iter()
+        .chain(MONSTER_CARDS.iter())
+        .chain(RUIN_CARDS.iter())
+        .chain(DEGREES.iter().flatten().map(|d| &d.name))
+}
+
+// finds a card by its id or its german or english name, ignoring case
+pub fn find(name: &str) -> Option<&'static CardName> {
+    let name = name.to_lowercase();
+    all_names().find(|n| {
+        [n.id, n.german, n.english]
+            .iter()
+            .any(|s| s.to_lowercase() == name)
+    })
+}
+
+pub fn find_degree(id: &str) -> Option<&'static Degree> {
+    DEGREES.iter().flatten().find(|d| d.name.id == id)
+}
+
+pub fn is_ruin(id: &str) -> bool {
+    RUIN_CARDS.iter().any(|n| n.id == id)
+}
+
+pub fn is_ambush(id: &str) -> bool {
+    MONSTER_CARDS.iter().any(|n| n.id == id)
+}
+
+// the english name followed by the server id, for logging
+pub fn describe(id: &str) -> String {
+    match all_names().find(|n| n.id == id) {
+        Some(n) => format!("{} ({})", n.english, n.id),
+        None => id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{explore_cards, monster_cards};
+
+    #[test]
+    fn every_card_has_a_name() {
+        let explore: Vec<_> = explore_cards()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let registry: Vec<_> = EXPLORE_CARDS.iter().map(|n| n.id).collect();
+        assert_eq!(explore, registry);
+
+        let monsters: Vec<_> = monster_cards()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let registry: Vec<_> = MONSTER_CARDS.iter().map(|n| n.id).collect();
+        assert_eq!(monsters, registry);
+
+        for id in ["tempelruinen", "verfallenerAussenposten"] {
+            assert!(is_ruin(id), "no ruin {}", id);
+        }
+    }
+
+    #[test]
+    fn every_degree_has_a_name() {
+        for category in ["wald", "wasser", "dorf", "distanz"] {
+            for number in 1..=4 {
+                let id = format!("{}{}", category, number);
+                assert!(find_degree(&id).is_some(), "no degree {}", id);
+            }
+        }
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for name in all_names() {
+            for s in [name.id, name.german, name.english] {
+                assert_eq!(find(s).map(|n| n.id), Some(name.id), "{} is ambiguous", s);
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    game::{PlayerBoard, Season},
+    names,
+    search::{find_best_moves, GameState, InitialState, SearchLimits, Turn},
+    transposition::TranspositionTable,
+};
+
+// the decisions of the bot in a server game, independent of where the messages come from
+pub struct Player<'a> {
+    game_state: GameState<'a>,
+    // after a reconnect, the state is rebuilt from the cards used in the current season
+    season_start: GameState<'a>,
+    // number of drawn cards of the current season which were already revealed
+    card_counter: usize,
+    // the ambushes of the current season, the server does not send them again after a reconnect
+    season_ambushes: Vec<String>,
+    // our own player id, learned from the first turn which is not an ambush, as we only place the
+    // ambushes on the boards of other players
+    player_id: Option<String>,
+    limits: SearchLimits,
+    // kept for the whole game, the key of a position covers everything its score depends on
+    table: TranspositionTable,
+}
+
+impl<'a> Player<'a> {
+    pub fn new(initial_state: &'a InitialState, limits: SearchLimits) -> Self {
+        let game_state = GameState::new(initial_state);
+        Self {
+            game_state,
+            season_start: game_state,
+            card_counter: 0,
+            season_ambushes: Vec::new(),
+            player_id: None,
+            limits,
+            table: TranspositionTable::new(),
+        }
+    }
+
+    // the server sends all cards of the current season again after a reconnect, except the
+    // ambushes, which take no time, so they can be revealed first
+    pub fn reconnected(&mut self) {
+        self.game_state = self.season_start;
+        self.card_counter = 0;
+        for card in &self.season_ambushes {
+            // known, as it was revealed before
+            let _ = self.game_state.reveal_card(card);
+        }
+    }
+
+    pub fn new_season(&mut self, season: Season) {
+        self.game_state.new_season(season);
+        self.season_start = self.game_state;
+        self.card_counter = 0;
+        self.season_ambushes.clear();
+    }
+
+    // reveals the newly drawn cards and returns all possible turns, the best first, the board is
+    // the one of a neighbor if the ambush of the turn is placed by us
+    pub fn new_turn(
+        &mut self,
+        player_id: &str,
+        board: PlayerBoard,
+        drawn_cards: &[String],
+    ) -> Vec<Turn> {
+        // the ruin of an earlier turn is covered, after an ambush it is listed again
+        let mut ruin_covered = true;
+
+        let mut is_ambush = false;
+        let mut card_known = true;
+        for c in &drawn_cards[self.card_counter..] {
+            // after a reconnect all cards of the season are revealed again, a ruin before an
+            // earlier explore card was covered by it
+            if ruin_covered {
+                self.game_state.set_on_ruin(false);
+            }
+
+            println!("Got card {}", names::describe(c));
+            match self.game_state.reveal_card(c) {
+                Ok(card) => {
+                    if card.is_some_and(|card| card.is_ambush()) {
+                        is_ambush = true;
+                        if !self.season_ambushes.contains(c) {
+                            self.season_ambushes.push(c.clone());
+                        }
+                    }
+                    // an ambush does not cover the ruin
+                    ruin_covered = card.is_some_and(|card| !card.is_ambush());
+                    card_known = true;
+                }
+                Err(err) => {
+                    println!("Skipping card: {}", err);
+                    ruin_covered = false;
+                    card_known = false;
+                }
+            }
+        }
+
+        // the server does not list an ambush in the used cards of the next turn
+        if !is_ambush {
+            self.card_counter = drawn_cards.len();
+            self.player_id.get_or_insert_with(|| player_id.to_string());
+        }
+
+        // before our first explore card it is not known whose board an ambush is placed on, so the
+        // board is not kept
+        let state = if self.player_id.as_deref() == Some(player_id) {
+            self.game_state.new_board(board);
+            self.game_state
+        } else {
+            println!("Placing the monster on the board of {}", player_id);
+            self.game_state.neighbor(board)
+        };
+
+        // without the current card a rift land is still a legal turn, so the turn is not missed
+        let card = if card_known {
+            drawn_cards.last().unwrap()
+        } else {
+            println!("Placing a rift land instead");
+            "splitterland"
+        };
+
+        // the search places an ambush where it costs the owner of the board the most points
+        let (ranking, statistics) = find_best_moves(&state, card, &self.limits, &self.table);
+        println!(
+            "Positions evaluated: {}, depth reached: {}{}",
+            statistics.positions_evaluated,
+            statistics.depth_reached,
+            if statistics.end_reached {
+                " (end of the game)"
+            } else {
+                ""
+            }
+        );
+
+        ranking.into_iter().map(|(t, _)| t).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        ambush::AmbushModel, game::PlayerTerrain, map::Map, mask::Mask, names::DEGREES,
+        scoring::Board, search::Engine,
+    };
+
+    const LIMITS: SearchLimits = SearchLimits {
+        duration: Duration::from_secs(10),
+        max_depth: Some(0),
+        max_positions: None,
+        threads: 1,
+        engine: Engine::Expectimax,
+        ambush: AmbushModel::WorstCase,
+    };
+
+    fn cards(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn covered_ruin_is_not_restored_after_reconnect() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let ruin = Map::side_a().ruin;
+        let board = PlayerBoard::new_with(|_| None);
+
+        let mut player = Player::new(&initial_state, LIMITS);
+        player.new_season(Season::Spring);
+        let turns = player.new_turn("p", board, &cards(&["tempelruinen", "ackerland"]));
+        assert!(turns.iter().all(|t| (t.cells & ruin).has_cells()));
+
+        player.reconnected();
+        let turns = player.new_turn("p", board, &cards(&["tempelruinen", "ackerland", "weiler"]));
+        assert!(turns.iter().any(|t| (t.cells & ruin).is_empty()));
+    }
+
+    #[test]
+    fn board_of_an_early_ambush_is_not_kept() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let neighbor_forest = Mask::from_cells(&[0, 1, 2]);
+        let neighbor_board = PlayerBoard::new_with(|idx| {
+            neighbor_forest
+                .contains(Mask::cell_idx(idx))
+                .then_some(PlayerTerrain::Forest)
+        });
+
+        let mut player = Player::new(&initial_state, LIMITS);
+        player.new_season(Season::Spring);
+        let turns = player.new_turn("neighbor", neighbor_board, &cards(&["koboldansturm"]));
+        assert!(turns.iter().all(|t| (t.cells & neighbor_forest).is_empty()));
+        assert!((player.game_state.forest() & neighbor_forest).is_empty());
+    }
+
+    #[test]
+    fn our_ambush_costs_the_neighbor_the_most_points() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let board = PlayerBoard::new_with(|_| None);
+        let random = SearchLimits {
+            ambush: AmbushModel::Random,
+            ..LIMITS
+        };
+
+        // the ambush model only describes how the neighbors place their monsters on our board
+        let placements = [LIMITS, random].map(|limits| {
+            let mut player = Player::new(&initial_state, limits);
+            player.new_season(Season::Spring);
+            player.new_turn("p", board, &cards(&["weiler"]));
+            let turns = player.new_turn("neighbor", board, &cards(&["weiler", "koboldansturm"]));
+            turns[0].cells
+        });
+        assert_eq!(placements[0], placements[1]);
+    }
+
+    #[test]
+    fn unknown_card_is_placed_as_a_rift_land() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let board = PlayerBoard::new_with(|_| None);
+
+        let mut player = Player::new(&initial_state, LIMITS);
+        player.new_season(Season::Spring);
+        let turns = player.new_turn("p", board, &cards(&["unbekannt"]));
+        assert!(!turns.is_empty());
+        assert!(turns.iter().all(|t| t.cells.count_cells() == 1));
+    }
+
+    #[test]
+    fn ambush_is_not_shuffled_back_after_reconnect() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let board = PlayerBoard::new_with(|_| None);
+
+        let states = [false, true].map(|reconnect| {
+            let mut player = Player::new(&initial_state, LIMITS);
+            player.new_season(Season::Spring);
+            player.new_turn("p", board, &cards(&["weiler"]));
+            player.new_turn("p", board, &cards(&["weiler", "koboldansturm"]));
+            if reconnect {
+                player.reconnected();
+            }
+            // the used cards of the server do not list the ambush
+            player.new_turn("p", board, &cards(&["weiler", "ackerland"]));
+            player.game_state.key()
+        });
+        assert_eq!(states[0], states[1]);
+    }
+}
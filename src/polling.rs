@@ -0,0 +1,399 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use native_tls::TlsConnector;
+use tungstenite::{http::Uri, stream::MaybeTlsStream, WebSocket};
+
+use crate::socketio::{self, EngineIOPacket, Error, Handshake, Result, SocketIOExt};
+
+trait Stream: Read + Write {}
+
+impl<S: Read + Write> Stream for S {}
+
+// a packet of a polling payload, binary packets are sent base64 encoded
+enum Packet {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+// the engine.io long-polling transport, every read is a GET request which is answered as soon as
+// the server has packets to send, and every write is a POST request
+pub struct Polling {
+    uri: Uri,
+    handshake: Handshake,
+    received: VecDeque<Packet>,
+}
+
+impl Polling {
+    // opens a new session, the uri is the websocket uri of the server
+    pub fn connect(uri: &Uri) -> Result<Self> {
+        let open_uri = socketio::transport_uri(uri, "polling", None)?;
+        let mut received = decode_payload(&request(&open_uri, "GET", "", None)?)?;
+
+        let handshake = match received.pop_front() {
+            Some(Packet::Text(msg)) => Handshake::from_open_packet(&msg)?,
+            _ => return Err(Error::EngineIO("expected open packet".into())),
+        };
+
+        Ok(Self {
+            // all further requests belong to the session
+            uri: socketio::transport_uri(uri, "polling", Some(&handshake.sid))?,
+            handshake,
+            received,
+        })
+    }
+
+    pub fn handshake(&self) -> &Handshake {
+        &self.handshake
+    }
+
+    // moves the session to a websocket, this has to happen before any other packets are exchanged
+    pub fn upgrade(&self) -> Result<WebSocket<MaybeTlsStream<TcpStream>>> {
+        if !self.handshake.upgrades.iter().any(|u| u == "websocket") {
+            return Err(Error::EngineIO(
+                "server does not allow websocket upgrades".into(),
+            ));
+        }
+
+        let uri = socketio::transport_uri(&self.uri, "websocket", Some(&self.handshake.sid))?;
+        let (mut socket, _) = tungstenite::connect(uri)?;
+
+        // probe the websocket before switching to it
+        socket.write_engineio_packet("2probe")?;
+        match socket.read_engineio_packet()? {
+            EngineIOPacket('3', data) if data == "probe" => {}
+            p => {
+                return Err(Error::EngineIO(format!(
+                    "expected probe response, instead got {:?}",
+                    p
+                )))
+            }
+        }
+        socket.write_engineio_packet("5")?;
+
+        Ok(socket)
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        loop {
+            if let Some(packet) = self.received.pop_front() {
+                return Ok(packet);
+            }
+
+            self.poll()?;
+        }
+    }
+
+    // waits for the next packets of the server
+    fn poll(&mut self) -> Result<()> {
+        let payload = request(&self.uri, "GET", "", Some(self.handshake.read_timeout()))?;
+        self.received.extend(decode_payload(&payload)?);
+
+        Ok(())
+    }
+}
+
+fn decode_payload(payload: &str) -> Result<VecDeque<Packet>> {
+    // packets are separated by the record separator
+    payload
+        .split('\x1e')
+        .filter(|p| !p.is_empty())
+        .map(|packet| match packet.strip_prefix('b') {
+            Some(data) => base64::decode(data)
+                .map(Packet::Binary)
+                .map_err(|err| Error::EngineIO(format!("invalid binary packet: {}", err))),
+            None => Ok(Packet::Text(packet.into())),
+        })
+        .collect()
+}
+
+// sends a request and returns the body of the response
+fn request(uri: &Uri, method: &str, body: &str, timeout: Option<Duration>) -> Result<String> {
+    // an ipv6 address is only written in brackets in the uri, the brackets are kept in the host
+    // header with the authority
+    let host = uri.host().unwrap_or_default();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    let secure = uri.scheme_str() == Some("https");
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    let stream = TcpStream::connect((host, port))?;
+    stream.set_read_timeout(timeout)?;
+    let mut stream: Box<dyn Stream> = if secure {
+        let connector = TlsConnector::new().map_err(|err| Error::Http(err.to_string()))?;
+        let stream = connector
+            .connect(host, stream)
+            .map_err(|err| Error::Http(err.to_string()))?;
+        Box::new(stream)
+    } else {
+        Box::new(stream)
+    };
+
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+        method,
+        path,
+        uri.authority().map_or(host, |a| a.as_str())
+    );
+    if method == "POST" {
+        request.push_str("Content-Type: text/plain;charset=UTF-8\r\n");
+        request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    // the server closes the connection after the response
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    parse_response(&response)
+}
+
+// returns the body of a successful response
+fn parse_response(response: &[u8]) -> Result<String> {
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| Error::Http("incomplete response".into()))?;
+    let head = std::str::from_utf8(&response[..head_end])
+        .map_err(|_| Error::Http("response header is not valid utf-8".into()))?;
+    let body = &response[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    if status_line.split(' ').nth(1) != Some("200") {
+        return Err(Error::Http(format!("unexpected response {}", status_line)));
+    }
+
+    let chunked = lines.any(|line| match line.split_once(':') {
+        Some((name, value)) => {
+            name.eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        }
+        None => false,
+    });
+
+    // a chunk might end in the middle of a character, so the body is only decoded as a whole
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+    String::from_utf8(body).map_err(|_| Error::Http("response body is not valid utf-8".into()))
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>> {
+    let invalid = || Error::Http("invalid chunked encoding".into());
+
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(invalid)?;
+        let size = std::str::from_utf8(&body[..line_end]).map_err(|_| invalid())?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+
+        let rest = &body[line_end + 2..];
+        decoded.extend_from_slice(rest.get(..size).ok_or_else(invalid)?);
+        body = rest.get(size + 2..).ok_or_else(invalid)?;
+    }
+}
+
+impl SocketIOExt for Polling {
+    fn read_raw_message(&mut self) -> Result<String> {
+        match self.next_packet()? {
+            Packet::Text(msg) => Ok(msg),
+            Packet::Binary(_) => Err(Error::EngineIO("unexpected binary packet".into())),
+        }
+    }
+
+    fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+        let timeout = Some(self.handshake.read_timeout());
+        match request(&self.uri, "POST", &msg.into(), timeout)?.as_str() {
+            "ok" => Ok(()),
+            body => Err(Error::EngineIO(format!(
+                "unexpected response to sent packet: {}",
+                body
+            ))),
+        }
+    }
+
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        match self.next_packet()? {
+            Packet::Binary(data) => Ok(data),
+            Packet::Text(msg) => Err(Error::EngineIO(format!(
+                "expected a binary packet, got {}",
+                msg
+            ))),
+        }
+    }
+
+    fn write_raw_binary(&mut self, data: &[u8]) -> Result<()> {
+        self.write_raw_message(format!("b{}", base64::encode(data)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use tungstenite::Message;
+
+    use super::*;
+
+    enum Reply {
+        Http(Vec<u8>),
+        // accepts a websocket and answers the probe of an upgrade
+        Upgrade,
+    }
+
+    // a server on a free local port, which answers each connection with the next reply and
+    // returns the received requests
+    fn stub_server(replies: Vec<Reply>) -> (Uri, thread::JoinHandle<Vec<String>>) {
+        stub_server_at("127.0.0.1:0", replies)
+    }
+
+    fn stub_server_at(addr: &str, replies: Vec<Reply>) -> (Uri, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind(addr).unwrap();
+        let uri = format!("ws://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+                match reply {
+                    Reply::Http(response) => {
+                        requests.push(read_request(&mut stream));
+                        stream.write_all(&response).unwrap();
+                    }
+                    Reply::Upgrade => {
+                        let mut socket = tungstenite::accept(stream).unwrap();
+                        for (expected, answer) in [("2probe", Some("3probe")), ("5", None)] {
+                            let msg = socket.read_message().unwrap();
+                            assert_eq!(msg, Message::Text(expected.into()));
+                            if let Some(answer) = answer {
+                                socket.write_message(Message::Text(answer.into())).unwrap();
+                            }
+                        }
+                        requests.push("upgraded".into());
+                    }
+                }
+            }
+            requests
+        });
+
+        (uri, server)
+    }
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut byte = [0];
+        while !request.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+
+        let request = String::from_utf8(request).unwrap();
+        let length = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map_or(0, |length| length.parse().unwrap());
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).unwrap();
+
+        request + &String::from_utf8(body).unwrap()
+    }
+
+    fn http_uri(uri: &Uri) -> Uri {
+        socketio::transport_uri(uri, "polling", None).unwrap()
+    }
+
+    #[test]
+    fn ipv6_address() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let (uri, server) = stub_server_at("[::1]:0", vec![Reply::Http(response.into())]);
+
+        let body = request(&http_uri(&uri), "GET", "", None).unwrap();
+        assert_eq!(body, "ok");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("\r\nHost: [::1]:"));
+    }
+
+    #[test]
+    fn plain_response() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nhällo";
+        let (uri, server) = stub_server(vec![Reply::Http(response.into())]);
+
+        let body = request(&http_uri(&uri), "POST", "2probe", None).unwrap();
+        assert_eq!(body, "hällo");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /socket.io/?"));
+        assert!(requests[0].ends_with("\r\n\r\n2probe"));
+    }
+
+    #[test]
+    fn chunked_response_splitting_a_character() {
+        // the two bytes of 'ä' are in different chunks
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            2\r\nh\xc3\r\n4;ext=1\r\n\xa4llo\r\n0\r\n\r\n";
+        let (uri, server) = stub_server(vec![Reply::Http(response.to_vec())]);
+
+        let body = request(&http_uri(&uri), "GET", "", None).unwrap();
+        assert_eq!(body, "hällo");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn error_response() {
+        let response = "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+        let (uri, server) = stub_server(vec![Reply::Http(response.into())]);
+
+        assert!(request(&http_uri(&uri), "GET", "", None).is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn upgrade_to_websocket() {
+        let open = concat!(
+            r#"0{"sid":"stub","upgrades":["websocket"],"pingInterval":25000,"#,
+            r#""pingTimeout":20000,"maxPayload":1000000}"#,
+            "\x1e40"
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            open.len(),
+            open
+        );
+        let (uri, server) = stub_server(vec![Reply::Http(response.into()), Reply::Upgrade]);
+
+        let mut polling = Polling::connect(&uri).unwrap();
+        assert_eq!(polling.handshake().sid, "stub");
+        // the packets after the open packet are kept
+        assert_eq!(polling.read_raw_message().unwrap(), "40");
+
+        polling.upgrade().unwrap();
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /socket.io/?"));
+        assert!(requests[0].contains("transport=polling"));
+        assert_eq!(requests[1], "upgraded");
+    }
+}
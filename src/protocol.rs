@@ -1,10 +1,17 @@
-use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+};
 
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use crate::{
     game::{PlayerBoard, PlayerTerrain, Season},
-    scoring::{self, Scoring},
+    map::{Map, MapTerrain},
+    mask::Mask,
+    names,
+    scoring::Scoring,
+    search::Turn,
 };
 
 pub enum Message {
@@ -13,11 +20,39 @@ pub enum Message {
     NewTurn {
         player_id: String,
         board: PlayerBoard,
+        map: Map,
         drawn_cards: Vec<String>,
     },
+    // whether the last turn was accepted by the server
+    ReceivedTurn(bool),
     FinalScoring(Value),
 }
 
+#[derive(Debug)]
+pub enum Error {
+    UnknownEvent(String),
+    Format(&'static str),
+    MissingField(&'static str),
+    UnknownDegree(String),
+    UnknownSeason(String),
+    UnknownTerrain(String),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownEvent(event) => write!(f, "unexpected game event {}", event),
+            Self::Format(msg) => write!(f, "{}", msg),
+            Self::MissingField(name) => write!(f, "missing field {}", name),
+            Self::UnknownDegree(name) => write!(f, "unknown degree {}", name),
+            Self::UnknownSeason(name) => write!(f, "unknown season {}", name),
+            Self::UnknownTerrain(name) => write!(f, "unknown terrain {}", name),
+        }
+    }
+}
+
 impl Debug for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -26,130 +61,224 @@ impl Debug for Message {
             Self::NewTurn {
                 player_id,
                 board,
+                map,
                 drawn_cards,
             } => f
                 .debug_struct("NewTurn")
                 .field("player_id", player_id)
                 .field("board", board)
+                .field("map", map)
                 .field("drawn_cards", drawn_cards)
                 .finish(),
+            Self::ReceivedTurn(valid) => f.debug_tuple("ReceivedTurn").field(valid).finish(),
             Self::FinalScoring(value) => f.debug_tuple("FinalScoring").field(value).finish(),
         }
     }
 }
 
 impl Message {
-    pub fn parse(event: &str, data: &Value) -> Option<Message> {
+    // returns None for events which are known but not relevant for the game
+    pub fn parse(event: &str, data: &Value) -> Result<Option<Message>> {
         match event {
             "newDegrees" => {
-                let cards = data.as_array().expect("expected an array of degrees");
-                Some(Self::NewDegrees([
-                    Self::parse_degree(&cards[0]),
-                    Self::parse_degree(&cards[1]),
-                    Self::parse_degree(&cards[2]),
-                    Self::parse_degree(&cards[3]),
-                ]))
+                let cards = data
+                    .as_array()
+                    .filter(|cards| cards.len() == 4)
+                    .ok_or(Error::Format("expected an array of four degrees"))?;
+                Ok(Some(Self::NewDegrees([
+                    Self::parse_degree(&cards[0])?,
+                    Self::parse_degree(&cards[1])?,
+                    Self::parse_degree(&cards[2])?,
+                    Self::parse_degree(&cards[3])?,
+                ])))
             }
-            "newSeason" => Some(Self::NewSeason(Self::parse_season(data))),
+            "newSeason" => Ok(Some(Self::NewSeason(Self::parse_season(data)?))),
             "newTurn" => {
-                let data = data.as_object().expect("expected a turn object");
-
-                let player_id = data["playerId"]
+                let player_id = field(data, "playerId")?
                     .as_str()
-                    .expect("expected a turn player id")
+                    .ok_or(Error::Format("expected a turn player id"))?
                     .to_string();
 
-                let board = Self::parse_board(&data["fields"]);
+                let (board, map) = parse_board(field(data, "fields")?)?;
 
-                let mut drawn_cards: Vec<_> = data["usedCards"]
+                let mut drawn_cards = field(data, "usedCards")?
                     .as_array()
-                    .expect("expected an array of cards")
+                    .ok_or(Error::Format("expected an array of cards"))?
                     .iter()
                     .map(Self::parse_card)
-                    .collect();
-                drawn_cards.push(Self::parse_card(&data["exploreCard"]));
+                    .collect::<Result<Vec<_>>>()?;
+                drawn_cards.push(Self::parse_card(field(data, "exploreCard")?)?);
 
-                Some(Self::NewTurn {
+                Ok(Some(Self::NewTurn {
                     player_id,
                     board,
+                    map,
                     drawn_cards,
-                })
+                }))
             }
-            "receivedTurn" => {
-                assert!(
-                    data.as_bool().expect("expected a turn validity boolean"),
-                    "turn was not valid"
-                );
-                None
-            }
-            "finalScoring" => Some(Self::FinalScoring(data.clone())),
-            "playerJoinsOrLeaves" | "scoring" => None,
-            _ => panic!("unexpected game event {}", event),
+            "receivedTurn" => Ok(Some(Self::ReceivedTurn(
+                data.as_bool()
+                    .ok_or(Error::Format("expected a turn validity boolean"))?,
+            ))),
+            "finalScoring" => Ok(Some(Self::FinalScoring(data.clone()))),
+            "playerJoinsOrLeaves" | "scoring" => Ok(None),
+            _ => Err(Error::UnknownEvent(event.into())),
         }
     }
 
-    fn parse_degree(value: &Value) -> Scoring {
-        match value.as_object().expect("expected a degree object")["card"]
+    fn parse_degree(value: &Value) -> Result<Scoring> {
+        let name = field(value, "card")?
             .as_str()
-            .expect("expected a card name")
-        {
-            "wald1" => scoring::stoneside_forest,
-            "wald2" => scoring::sentinel_wood,
-            "wald3" => scoring::treetower,
-            "wald4" => scoring::greenbough,
-            "wasser1" => scoring::mage_valley,
-            "wasser2" => scoring::canal_lake,
-            "wasser3" => scoring::shoreside_expanse,
-            "wasser4" => scoring::the_golden_granary,
-            "dorf1" => scoring::greengold_plains,
-            "dorf2" => scoring::shieldgate,
-            "dorf3" => scoring::wildholds,
-            "dorf4" => scoring::great_city,
-            "distanz1" => scoring::borderlands,
-            "distanz2" => scoring::the_cauldrons,
-            "distanz3" => scoring::the_broken_road,
-            "distanz4" => scoring::lost_barony,
-            name => panic!("unknown degree {}", name),
-        }
+            .ok_or(Error::Format("expected a card name"))?;
+
+        names::find_degree(name)
+            .map(|degree| degree.scoring)
+            .ok_or_else(|| Error::UnknownDegree(name.into()))
     }
 
-    fn parse_season(value: &Value) -> Season {
-        match value.as_object().expect("expected a season object")["name"]
+    fn parse_season(value: &Value) -> Result<Season> {
+        match field(value, "name")?
             .as_str()
-            .expect("expected a season name")
+            .ok_or(Error::Format("expected a season name"))?
         {
-            "spring" => Season::Spring,
-            "sommer" => Season::Summer,
-            "autmn" => Season::Fall,
-            "winter" => Season::Winter,
-            name => panic!("unknown season {}", name),
+            "spring" => Ok(Season::Spring),
+            "sommer" => Ok(Season::Summer),
+            "autmn" => Ok(Season::Fall),
+            "winter" => Ok(Season::Winter),
+            name => Err(Error::UnknownSeason(name.into())),
         }
     }
 
-    fn parse_board(value: &Value) -> PlayerBoard {
-        let cells = value.as_array().expect("expected an array of cells");
-        PlayerBoard::new_with(|i| {
-            match cells[i as usize]
-                .as_object()
-                .expect("expected a cell object")["landscape"]
+    fn parse_card(value: &Value) -> Result<String> {
+        Ok(field(value, "name")?
+            .as_str()
+            .ok_or(Error::Format("expected a card name"))?
+            .into())
+    }
+}
+
+// the fields of a newTurn event
+pub fn parse_board(value: &Value) -> Result<(PlayerBoard, Map)> {
+    let cells = value
+        .as_array()
+        .filter(|cells| cells.len() == Mask::CELL_COUNT as usize)
+        .ok_or(Error::Format("expected an array of cells"))?
+        .iter()
+        .map(|cell| {
+            field(cell, "landscape")?
                 .as_str()
-                .expect("expected a terrain name")
-            {
-                "FOREST" => Some(PlayerTerrain::Forest),
-                "VILLAGE" => Some(PlayerTerrain::Village),
-                "FARM" => Some(PlayerTerrain::Farm),
-                "WATER" => Some(PlayerTerrain::Water),
-                "MONSTER" => Some(PlayerTerrain::Monster),
-                "MOUNTAIN" | "WASTELAND" | "Ruin" | "EMPTY" => None,
-                name => panic!("unknown tarrain {}", name),
-            }
+                .ok_or(Error::Format("expected a terrain name"))
         })
+        .collect::<Result<Vec<_>>>()?;
+
+    let landscapes = cells
+        .iter()
+        .map(|&name| parse_landscape(name).ok_or_else(|| Error::UnknownTerrain(name.into())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let board = PlayerBoard::new_with(|i| match landscapes[i as usize] {
+        Landscape::Player(terrain) => Some(terrain),
+        _ => None,
+    });
+
+    // ruins are only visible as long as they are not covered, so this is only complete for an
+    // empty board
+    let map = Map::new_with(|i| match landscapes[i as usize] {
+        Landscape::Map(terrain) => Some(terrain),
+        _ => None,
+    });
+
+    Ok((board, map))
+}
+
+// the payload of the finishTurn event
+pub fn turn_payload(player_id: &str, turn: &Turn) -> Value {
+    let terrain_name = terrain_name(turn.terrain);
+
+    let mut fields = HashMap::new();
+    for pos in turn.cells.cells() {
+        fields.insert(pos.to_string(), terrain_name);
     }
 
-    fn parse_card(value: &Value) -> String {
-        value.as_object().expect("expected a card object")["name"]
+    json!({ "playerId": player_id, "fields": fields })
+}
+
+// the player id, terrain and cells of a finishTurn payload
+pub fn parse_turn(data: &Value) -> Result<(String, PlayerTerrain, Mask)> {
+    let player_id = field(data, "playerId")?
+        .as_str()
+        .ok_or(Error::Format("expected a turn player id"))?;
+
+    let fields = field(data, "fields")?
+        .as_object()
+        .ok_or(Error::Format("expected an object of cells"))?;
+
+    let mut cells = Mask::empty();
+    let mut terrain = None;
+    for (idx, name) in fields {
+        let idx = idx
+            .parse()
+            .ok()
+            .filter(|&idx| idx < Mask::CELL_COUNT)
+            .ok_or(Error::Format("expected cell indices"))?;
+        cells |= Mask::cell_idx(idx);
+
+        let name = name
             .as_str()
-            .expect("expected a card name")
-            .into()
+            .ok_or(Error::Format("expected a terrain name"))?;
+        let t = parse_terrain(name).ok_or_else(|| Error::UnknownTerrain(name.into()))?;
+        if terrain.replace(t).is_some_and(|prev| prev != t) {
+            return Err(Error::Format("expected cells of a single terrain"));
+        }
     }
+
+    let terrain = terrain.ok_or(Error::Format("expected at least one cell"))?;
+    Ok((player_id.into(), terrain, cells))
+}
+
+// what a cell of the board shows
+#[derive(Clone, Copy)]
+enum Landscape {
+    Player(PlayerTerrain),
+    Map(MapTerrain),
+    Empty,
+}
+
+// the names of the server for every landscape
+const LANDSCAPES: [(&str, Landscape); 9] = [
+    ("FOREST", Landscape::Player(PlayerTerrain::Forest)),
+    ("VILLAGE", Landscape::Player(PlayerTerrain::Village)),
+    ("FARM", Landscape::Player(PlayerTerrain::Farm)),
+    ("WATER", Landscape::Player(PlayerTerrain::Water)),
+    ("MONSTER", Landscape::Player(PlayerTerrain::Monster)),
+    ("MOUNTAIN", Landscape::Map(MapTerrain::Mountain)),
+    ("WASTELAND", Landscape::Map(MapTerrain::Wasteland)),
+    ("Ruin", Landscape::Map(MapTerrain::Ruin)),
+    ("EMPTY", Landscape::Empty),
+];
+
+fn parse_landscape(name: &str) -> Option<Landscape> {
+    LANDSCAPES
+        .iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, landscape)| landscape)
+}
+
+fn terrain_name(terrain: PlayerTerrain) -> &'static str {
+    LANDSCAPES
+        .iter()
+        .find(|(_, landscape)| matches!(landscape, Landscape::Player(t) if *t == terrain))
+        .map(|&(name, _)| name)
+        .expect("every terrain has a name")
+}
+
+fn parse_terrain(name: &str) -> Option<PlayerTerrain> {
+    match parse_landscape(name) {
+        Some(Landscape::Player(terrain)) => Some(terrain),
+        _ => None,
+    }
+}
+
+fn field<'a>(value: &'a Value, name: &'static str) -> Result<&'a Value> {
+    value.get(name).ok_or(Error::MissingField(name))
 }
@@ -0,0 +1,200 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Received,
+    Sent,
+    // the connection was reestablished, the event is empty
+    Reconnected,
+}
+
+impl Direction {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Sent => "sent",
+            Self::Reconnected => "reconnected",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "received" => Some(Self::Received),
+            "sent" => Some(Self::Sent),
+            "reconnected" => Some(Self::Reconnected),
+            _ => None,
+        }
+    }
+}
+
+// a recorded event, the time is in milliseconds since the unix epoch
+pub struct Entry {
+    pub time: u64,
+    pub direction: Direction,
+    pub event: String,
+    pub data: Option<Value>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Format(String),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read recording: {}", err),
+            Self::Json(err) => write!(f, "invalid json in recording: {}", err),
+            Self::Format(msg) => write!(f, "invalid recording: {}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+// writes all events of a session to a file, one json object per line
+pub struct Recorder {
+    file: LineWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            file: LineWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        direction: Direction,
+        event: &str,
+        data: Option<&Value>,
+    ) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_millis() as u64);
+
+        let entry = json!({
+            "time": time,
+            "direction": direction.name(),
+            "event": event,
+            "data": data,
+        });
+        writeln!(self.file, "{}", entry)
+    }
+}
+
+// reads all entries of a file written by a `Recorder`, the last line is cut off if the bot was
+// stopped while writing it
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let lines = BufReader::new(File::open(path)?)
+        .lines()
+        .collect::<io::Result<Vec<_>>>()?;
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = |what| Error::Format(format!("{} in line {}", what, i + 1));
+
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(err) if err.is_eof() && i + 1 == lines.len() => {
+                println!("Skipping truncated line {}", i + 1);
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        entries.push(Entry {
+            time: value
+                .get("time")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid("missing time"))?,
+            direction: value
+                .get("direction")
+                .and_then(Value::as_str)
+                .and_then(Direction::from_name)
+                .ok_or_else(|| invalid("unknown direction"))?,
+            event: value
+                .get("event")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .into(),
+            data: value.get("data").filter(|data| !data.is_null()).cloned(),
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn recorded_events_are_loaded_again() {
+        let path = env::temp_dir().join(format!(
+            "cartographers-recorder-{}.jsonl",
+            std::process::id()
+        ));
+        let events = [
+            (Direction::Received, "newSeason", Some(json!("spring"))),
+            (Direction::Sent, "enterGame", Some(json!("bot"))),
+            (Direction::Reconnected, "", None),
+            (
+                Direction::Received,
+                "newTurn",
+                Some(json!({ "drawnCards": ["weiler"] })),
+            ),
+        ];
+
+        let mut recorder = Recorder::create(&path).unwrap();
+        for (direction, event, data) in &events {
+            recorder.record(*direction, event, data.as_ref()).unwrap();
+        }
+        drop(recorder);
+        // the bot was stopped while writing the next event
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str(r#"{"time":1,"direction":"sent","event":"finishT"#);
+        fs::write(&path, content).unwrap();
+
+        let entries = load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded: Vec<_> = entries
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.direction, entry.event, entry.data))
+            .collect();
+        let expected: Vec<_> = events
+            .into_iter()
+            .map(|(direction, event, data)| (direction, event.to_string(), data))
+            .collect();
+        assert_eq!(loaded, expected);
+    }
+}
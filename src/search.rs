@@ -1,24 +1,31 @@
 use std::{
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    collections::hash_map::DefaultHasher,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use enum_map::{enum_map, EnumMap};
 
 use crate::{
+    ambush::AmbushModel,
     card::{explore_cards, monster_cards, splitterland, splitterland_monster, ExploreCard},
     deck::{Deck, DrawnCard},
     game::{PlayerBoard, PlayerTerrain, Season},
+    map::Map,
     mask::Mask,
+    monte_carlo,
+    names::RUIN_CARDS,
     scoring::{monsters, mountain_gold, Board, Score, Scoring},
+    transposition::{Bound, TranspositionTable},
 };
 
 // state that does not change during the game
 pub struct InitialState {
+    degrees: [Scoring; 4],
     deck: Vec<ExploreCard>,
     monster_deck: Vec<ExploreCard>,
     // all scoring relevant for each season,(four degrees + gold + monsters), with factor
@@ -29,7 +36,7 @@ pub struct InitialState {
 }
 
 impl InitialState {
-    pub fn new(degrees: [Scoring; 4]) -> Self {
+    pub fn new(degrees: [Scoring; 4], map: Map) -> Self {
         let scoring = enum_map! {
             Season::Spring => [(degrees[0], 2.0), (degrees[1], 2.0), (degrees[2], 1.0), (degrees[3], 1.0), (gold, 4.0), (monsters, 4.0)],
             Season::Summer => [(degrees[0], 0.5), (degrees[1], 1.0), (degrees[2], 2.0), (degrees[3], 1.0), (gold, 3.0), (monsters, 3.0)],
@@ -38,12 +45,13 @@ impl InitialState {
         };
 
         Self {
+            degrees,
             deck: explore_cards(),
             monster_deck: monster_cards(),
             scoring,
-            ruin: Mask::from_cells(&[16, 23, 31, 89, 97, 104]),
-            mountain: Mask::from_cells(&[14, 30, 60, 90, 106]),
-            wasteland: Mask::empty(),
+            ruin: map.ruin,
+            mountain: map.mountain,
+            wasteland: map.wasteland,
         }
     }
 }
@@ -58,6 +66,8 @@ pub struct GameState<'a> {
     board: PlayerBoard,
     gold: i16,
     total_score: i16,
+    // a ruin was drawn, the next explore card has to cover a ruin if possible
+    on_ruin: bool,
 }
 
 impl GameState<'_> {
@@ -70,24 +80,32 @@ impl GameState<'_> {
             board: PlayerBoard::new_with(|_| None),
             gold: 0,
             total_score: 0,
+            on_ruin: false,
         }
     }
 
     pub fn new_season(&mut self, season: Season) {
         self.season = season;
         self.season_timer = 0;
+        self.on_ruin = false;
         self.deck.new_season();
     }
 
-    pub fn reveal_card(&mut self, card: &str) -> &ExploreCard {
-        if let Some(idx) = self
+    // returns None for a ruin
+    pub fn reveal_card(&mut self, card: &str) -> Result<Option<&ExploreCard>, UnknownCard> {
+        if let Some(idx) = RUIN_CARDS.iter().position(|c| c.id == card) {
+            self.deck.remove_ruin_card(idx as u16);
+            self.on_ruin = true;
+            Ok(None)
+        } else if let Some(idx) = self
             .initial_state
             .deck
             .iter()
             .position(|c| c.name() == card)
         {
             self.deck.remove_explore_card(idx as u16);
-            &self.initial_state.deck[idx]
+            self.season_timer += self.initial_state.deck[idx].time();
+            Ok(Some(&self.initial_state.deck[idx]))
         } else if let Some(idx) = self
             .initial_state
             .monster_deck
@@ -95,15 +113,82 @@ impl GameState<'_> {
             .position(|c| c.name() == card)
         {
             self.deck.remove_monster_card(idx as u16);
-            &self.initial_state.monster_deck[idx]
+            Ok(Some(&self.initial_state.monster_deck[idx]))
         } else {
-            panic!("card {} was not found in deck", card);
+            Err(UnknownCard(card.into()))
         }
     }
 
+    // only needed if the ruin is not known from the revealed cards
+    pub fn set_on_ruin(&mut self, on_ruin: bool) {
+        self.on_ruin = on_ruin;
+    }
+
     pub fn new_board(&mut self, board: PlayerBoard) {
         self.board = board;
     }
+
+    // the state of another player: all players draw the same cards, but the gold and the score of
+    // the neighbor are not known
+    pub fn neighbor(&self, board: PlayerBoard) -> Self {
+        Self {
+            board,
+            gold: 0,
+            total_score: 0,
+            ..*self
+        }
+    }
+
+    pub fn apply_turn(&mut self, turn: &Turn) {
+        *self = self
+            .add_gold(turn.gold)
+            .place_cells(turn.terrain, turn.cells);
+    }
+
+    pub fn season_timer(&self) -> u16 {
+        self.season_timer
+    }
+
+    // only needed if the timer is not known from the revealed cards
+    pub fn set_season_timer(&mut self, timer: u16) {
+        self.season_timer = timer;
+    }
+
+    // the scores of the current season, if it would end now
+    pub fn season_score(&self) -> SeasonScore {
+        let [a, b] = self.season.degrees();
+        SeasonScore {
+            degrees: [
+                (a, self.initial_state.degrees[a](self)),
+                (b, self.initial_state.degrees[b](self)),
+            ],
+            gold: gold(self),
+            monsters: monsters(self),
+        }
+    }
+}
+
+// a card which is not part of the game
+#[derive(Debug)]
+pub struct UnknownCard(pub String);
+
+impl Display for UnknownCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown card {}", self.0)
+    }
+}
+
+pub struct SeasonScore {
+    // index of the degree with its score
+    pub degrees: [(usize, Score); 2],
+    pub gold: Score,
+    pub monsters: Score,
+}
+
+impl SeasonScore {
+    pub fn total(&self) -> Score {
+        self.degrees[0].1 + self.degrees[1].1 + self.gold + self.monsters
+    }
 }
 
 fn gold(state: &GameState) -> Score {
@@ -114,24 +199,146 @@ fn zero(_: &GameState) -> Score {
     0
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Turn {
     pub terrain: PlayerTerrain,
     pub cells: Mask,
+    pub gold: i16,
+}
+
+// the search stops at whichever limit is reached first
+#[derive(Clone, Copy)]
+pub struct SearchLimits {
+    pub duration: Duration,
+    pub max_depth: Option<u32>,
+    pub max_positions: Option<u32>,
+    // number of threads the turns of the root are split across
+    pub threads: usize,
+    pub engine: Engine,
+    // how the neighbors place the ambushes on our board in the lookahead, our own placements on
+    // their boards always cost them the most points
+    pub ambush: AmbushModel,
+}
+
+#[derive(Clone, Copy)]
+pub enum Engine {
+    // depth limited, with the heuristic score at the leaves
+    Expectimax,
+    // flat monte carlo, plays out whole games after the turns of the root without growing a
+    // tree, the depth limit is ignored
+    MonteCarlo(Rollout),
+}
+
+// how the turns of a played out game are chosen
+#[derive(Clone, Copy)]
+pub enum Rollout {
+    Random,
+    // the turn with the best heuristic score
+    Greedy,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "expectimax" => Ok(Self::Expectimax),
+            "monte-carlo-greedy" => Ok(Self::MonteCarlo(Rollout::Greedy)),
+            "monte-carlo-random" => Ok(Self::MonteCarlo(Rollout::Random)),
+            _ => Err(format!("unknown engine {}", s)),
+        }
+    }
+}
+
+// the clock is only read every this many positions
+const TIME_CHECK_INTERVAL: u32 = 1024;
+
+// the state of a running search, shared by all nodes of all threads
+struct Search<'a> {
+    positions_evaluated: AtomicU32,
+    out_of_time: AtomicBool,
+    // a position was skipped because of a limit, so the running iteration is incomplete
+    aborted: AtomicBool,
+    // positions of the running iteration scored by the heuristic instead of the end of the game
+    horizon_positions: AtomicU32,
+    deadline: Instant,
+    max_positions: u32,
+    // kept for all iterations, deeper results replace the shallower ones
+    table: &'a TranspositionTable,
+    // lower and upper bound of all scores
+    bounds: (f32, f32),
+    ambush: AmbushModel,
+}
+
+impl Search<'_> {
+    fn count_position(&self) {
+        let positions = self.positions_evaluated.fetch_add(1, Ordering::Relaxed) + 1;
+        if positions.is_multiple_of(TIME_CHECK_INTERVAL) && Instant::now() >= self.deadline {
+            self.out_of_time.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // whether the position has to be skipped because one of the limits is reached
+    fn abort(&self) -> bool {
+        let limit_reached = self.out_of_time.load(Ordering::Relaxed)
+            || self.positions_evaluated.load(Ordering::Relaxed) >= self.max_positions;
+        if limit_reached {
+            self.aborted.store(true, Ordering::Relaxed);
+        }
+        limit_reached
+    }
+
+    // a score is valid for any depth if no position below it was scored by the heuristic, which
+    // is only known for sure if no other thread did so in the meantime
+    fn store(&self, key: u64, depth: u32, horizon_before: u32, score: f32, bound: Bound) -> f32 {
+        // the score of an aborted search is incomplete
+        if !self.aborted.load(Ordering::Relaxed) {
+            let end_reached = self.horizon_positions.load(Ordering::Relaxed) == horizon_before;
+            self.table.insert(key, depth, end_reached, score, bound);
+        }
+
+        score
+    }
 }
 
 pub struct Statistics {
     pub positions_evaluated: u32,
     pub depth_reached: u32,
+    // every line of the search reached the end of the game, a deeper search gives the same scores
     pub end_reached: bool,
 }
 
 pub fn find_best_move(
     state: &GameState,
     card: &str,
-    on_ruin: bool,
-    duration: Duration,
+    limits: &SearchLimits,
+    table: &TranspositionTable,
 ) -> (Turn, Statistics) {
+    // only the best turn is needed, so the other turns can be cut off by its score
+    let (ranking, statistics) = rank_turns(state, card, limits, table, true);
+    (ranking[0].0, statistics)
+}
+
+// all possible turns with their expected score, the best turn first, the table can be kept for
+// all turns of a game
+pub fn find_best_moves(
+    state: &GameState,
+    card: &str,
+    limits: &SearchLimits,
+    table: &TranspositionTable,
+) -> (Vec<(Turn, f32)>, Statistics) {
+    rank_turns(state, card, limits, table, false)
+}
+
+// with root pruning only the score of the best turn is exact, the others can be bounds which rank
+// them worse than they are
+fn rank_turns(
+    state: &GameState,
+    card: &str,
+    limits: &SearchLimits,
+    table: &TranspositionTable,
+    root_pruning: bool,
+) -> (Vec<(Turn, f32)>, Statistics) {
     let card = state
         .initial_state
         .deck
@@ -146,58 +353,218 @@ pub fn find_best_move(
                 .expect("card was not found")
         });
 
-    let mut statistics = Statistics {
-        positions_evaluated: 0,
-        depth_reached: 0,
-        end_reached: false,
-    };
+    let mut turns = Vec::new();
+    for_each_turn(state, card, |turn, state| turns.push((turn, state)));
+    assert!(!turns.is_empty(), "no possible turn found");
 
-    let timeout_reached = Arc::new(AtomicBool::new(false));
-    let timeout_reached_clone = timeout_reached.clone();
-    let mut best_turn = None;
+    let (mut ranking, statistics) = match limits.engine {
+        Engine::Expectimax => {
+            search_expectimax(state, &turns, card.is_ambush(), limits, table, root_pruning)
+        }
+        Engine::MonteCarlo(rollout) => {
+            monte_carlo::search(&turns, card.is_ambush(), rollout, limits, state.key())
+                .unwrap_or_else(|| {
+                    // the limits are used up, so only the heuristic scores are left
+                    let limits = SearchLimits {
+                        max_depth: Some(0),
+                        ..*limits
+                    };
+                    search_expectimax(
+                        state,
+                        &turns,
+                        card.is_ambush(),
+                        &limits,
+                        table,
+                        root_pruning,
+                    )
+                })
+        }
+    };
 
-    thread::spawn(move || {
-        thread::sleep(duration);
-        timeout_reached_clone.store(true, Ordering::Relaxed);
+    // an ambush is placed to minimize our score
+    ranking.sort_by(|(_, a), (_, b)| {
+        if card.is_ambush() {
+            a.total_cmp(b)
+        } else {
+            b.total_cmp(a)
+        }
     });
 
-    for depth in 0.. {
-        let (turn, _) = search_explore_move(
-            state,
-            card,
-            on_ruin,
+    (ranking, statistics)
+}
+
+// iterative deepening until one of the limits is reached
+fn search_expectimax(
+    state: &GameState,
+    turns: &[(Turn, GameState)],
+    is_ambush: bool,
+    limits: &SearchLimits,
+    table: &TranspositionTable,
+    root_pruning: bool,
+) -> (Vec<(Turn, f32)>, Statistics) {
+    let search = Search {
+        positions_evaluated: AtomicU32::new(0),
+        out_of_time: AtomicBool::new(false),
+        aborted: AtomicBool::new(false),
+        horizon_positions: AtomicU32::new(0),
+        deadline: Instant::now() + limits.duration,
+        max_positions: limits.max_positions.unwrap_or(u32::MAX),
+        table,
+        bounds: state.score_bounds(),
+        ambush: limits.ambush,
+    };
+    let mut depth_reached = 0;
+    let mut end_reached = false;
+    let mut best_ranking = Vec::new();
+    // sorted by the scores of the previous iteration, so the best turns are searched first
+    let mut turns = turns.to_vec();
+
+    for depth in 0..=limits.max_depth.unwrap_or(u32::MAX) {
+        // a small search might not read the clock during an iteration
+        if depth > 0 && Instant::now() >= search.deadline {
+            break;
+        }
+
+        search.horizon_positions.store(0, Ordering::Relaxed);
+        let scores = search_turns(
+            &turns,
             depth,
-            &mut statistics,
-            &timeout_reached,
-            false,
+            is_ambush,
+            root_pruning,
+            &search,
+            limits.threads,
         );
 
-        if timeout_reached.load(Ordering::Relaxed) {
+        // the first iteration is never aborted, so there is always a turn, a later one is kept if
+        // it was completed even if a limit was reached at its end
+        if search.aborted.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut ranking: Vec<_> = turns.into_iter().zip(scores).collect();
+        ranking.sort_by(|(_, a), (_, b)| {
+            if is_ambush {
+                a.total_cmp(b)
+            } else {
+                b.total_cmp(a)
+            }
+        });
+        turns = ranking.iter().map(|&(turn, _)| turn).collect();
+        best_ranking = ranking
+            .into_iter()
+            .map(|((turn, _), score)| (turn, score))
+            .collect();
+        depth_reached = depth;
+
+        end_reached = search.horizon_positions.load(Ordering::Relaxed) == 0;
+        if end_reached {
             break;
-        } else {
-            best_turn = Some(turn);
-            statistics.depth_reached = depth;
         }
     }
 
-    (best_turn.unwrap(), statistics)
+    let statistics = Statistics {
+        positions_evaluated: search.positions_evaluated.into_inner(),
+        depth_reached,
+        end_reached,
+    };
+
+    (best_ranking, statistics)
 }
 
-fn search_explore_move(
-    state: &GameState,
+// the scores of the turns in the same order, each thread takes the next turn not searched yet,
+// with root pruning a turn which is not better than the best one searched so far only gets a bound
+fn search_turns(
+    turns: &[(Turn, GameState)],
+    depth: u32,
+    is_ambush: bool,
+    root_pruning: bool,
+    search: &Search,
+    threads: usize,
+) -> Vec<f32> {
+    let next_turn = AtomicUsize::new(0);
+    let mut scores = vec![0.0; turns.len()];
+    // the bits of the best score so far, an ambush is placed to minimize our score
+    let worst_score = if is_ambush {
+        f32::INFINITY
+    } else {
+        f32::NEG_INFINITY
+    };
+    let best_score = AtomicU32::new(worst_score.to_bits());
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, turns.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut scores = Vec::new();
+                    loop {
+                        let idx = next_turn.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(_, mut state)) = turns.get(idx) else {
+                            break scores;
+                        };
+                        let best = f32::from_bits(best_score.load(Ordering::Relaxed));
+                        let (alpha, beta) = if !root_pruning {
+                            (f32::NEG_INFINITY, f32::INFINITY)
+                        } else if is_ambush {
+                            (f32::NEG_INFINITY, best)
+                        } else {
+                            (best, f32::INFINITY)
+                        };
+                        let score = search_game_move(&mut state, depth, search, alpha, beta);
+                        scores.push((idx, score));
+
+                        // another thread might have found a better score in the meantime
+                        let _ =
+                            best_score.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |best| {
+                                let best = f32::from_bits(best);
+                                let is_better = if is_ambush {
+                                    score < best
+                                } else {
+                                    score > best
+                                };
+                                is_better.then_some(score.to_bits())
+                            });
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (idx, score) in worker.join().unwrap() {
+                scores[idx] = score;
+            }
+        }
+    });
+
+    scores
+}
+
+// calls f with every possible turn and the state after it
+pub fn for_each_turn<'a>(
+    state: &GameState<'a>,
+    card: &ExploreCard,
+    mut f: impl FnMut(Turn, GameState<'a>),
+) {
+    if !place_card(state, card, state.on_ruin, &mut f) {
+        // there was no possible turn, try to place a rift land anywhere
+        let rift_land = if card.is_ambush() {
+            splitterland_monster()
+        } else {
+            splitterland()
+        };
+        place_card(state, &rift_land, false, &mut f);
+    }
+}
+
+// returns false if the card cannot be placed
+fn place_card<'a>(
+    state: &GameState<'a>,
     card: &ExploreCard,
     on_ruin: bool,
-    depth: u32,
-    statistics: &mut Statistics,
-    timeout_reached: &AtomicBool,
-    tried_rift_land: bool,
-) -> (Turn, f32) {
+    f: &mut impl FnMut(Turn, GameState<'a>),
+) -> bool {
     let is_ambush = card.is_ambush();
-    let mut best_score = if is_ambush { f32::MAX } else { f32::MIN };
-    let mut best_turn = None;
-
     let empty = state.empty();
     let ruin = state.ruin();
+    let mut placed = false;
 
     // check all possible terrains and patterns at all positions
     for &(pattern, gold) in card.patterns() {
@@ -209,86 +576,167 @@ fn search_explore_move(
                     continue;
                 }
 
-                let mut state = state.place_cells(terrain, cells);
-                let score = search_game_move(&mut state, depth, statistics, timeout_reached);
-
-                let is_better = if is_ambush {
-                    score < best_score
-                } else {
-                    score > best_score
+                placed = true;
+                let turn = Turn {
+                    terrain,
+                    cells,
+                    gold,
                 };
+                f(turn, state.place_cells(terrain, cells));
+            }
+        }
+    }
 
-                if is_better {
-                    best_score = score;
-                    best_turn = Some(Turn { terrain, cells });
-                }
+    placed
+}
+
+// the score is exact if it is inside of the window (alpha, beta), otherwise it is only a bound
+fn search_explore_move(
+    state: &GameState,
+    card: &ExploreCard,
+    depth: u32,
+    search: &Search,
+    alpha: f32,
+    beta: f32,
+) -> f32 {
+    let is_ambush = card.is_ambush();
+    // without a possible turn nothing is placed
+    let mut unchanged = *state;
+
+    match search.ambush {
+        AmbushModel::Random if is_ambush => {
+            let mut score_sum = 0.0;
+            let mut turn_count = 0;
+            for_each_turn(state, card, |_, mut state| {
+                score_sum +=
+                    search_game_move(&mut state, depth, search, f32::NEG_INFINITY, f32::INFINITY);
+                turn_count += 1;
+            });
+            if turn_count == 0 {
+                return search_game_move(&mut unchanged, depth, search, alpha, beta);
             }
+            return score_sum / turn_count as f32;
         }
+        _ => {}
     }
 
-    if best_turn.is_none() && !tried_rift_land {
-        // there was no possible turn, try to place a rift land anywhere
-        let rift_land = if is_ambush {
-            splitterland_monster()
-        } else {
-            splitterland()
+    let mut best_score = None;
+
+    for_each_turn(state, card, |_, mut state| {
+        // the remaining turns cannot change the result anymore
+        let cutoff = best_score.is_some_and(|best_score| {
+            if is_ambush {
+                best_score <= alpha
+            } else {
+                best_score >= beta
+            }
+        });
+        if cutoff {
+            return;
+        }
+
+        let score = match best_score {
+            None => search_game_move(&mut state, depth, search, alpha, beta),
+            Some(best_score) if is_ambush => {
+                search_game_move(&mut state, depth, search, alpha, beta.min(best_score))
+            }
+            Some(best_score) => {
+                search_game_move(&mut state, depth, search, alpha.max(best_score), beta)
+            }
         };
-        return search_explore_move(
-            state,
-            &rift_land,
-            false,
-            depth,
-            statistics,
-            timeout_reached,
-            true,
-        );
-    }
 
-    (best_turn.expect("no possible turn found"), best_score)
+        let is_better = best_score.is_none_or(|best_score| {
+            if is_ambush {
+                score < best_score
+            } else {
+                score > best_score
+            }
+        });
+
+        if is_better {
+            best_score = Some(score);
+        }
+    });
+
+    best_score.unwrap_or_else(|| search_game_move(&mut unchanged, depth, search, alpha, beta))
 }
 
+// star1 pruning: the scores of the remaining cards are bounded, so a card whose score is outside
+// of its share of the window decides that the result is outside of the window
 fn search_game_move(
     state: &mut GameState,
     depth: u32,
-    statistics: &mut Statistics,
-    timeout_reached: &AtomicBool,
+    search: &Search,
+    alpha: f32,
+    beta: f32,
 ) -> f32 {
-    if timeout_reached.load(Ordering::Relaxed) {
-        return 0.0;
-    }
-
-    statistics.positions_evaluated += 1;
+    search.count_position();
 
     if !state.handle_season_end() {
         // game has ended
-        statistics.end_reached = true;
         return state.final_score();
     }
 
     if depth == 0 {
+        search.horizon_positions.fetch_add(1, Ordering::Relaxed);
         return state.heuristic_score();
     }
 
+    if search.abort() {
+        return 0.0;
+    }
+
+    // the same position is reached by drawing the cards in a different order
+    let key = state.key();
+    if let Some((score, bound, end_reached)) = search.table.get(key, depth) {
+        let usable = match bound {
+            Bound::Exact => true,
+            Bound::Lower => score >= beta,
+            Bound::Upper => score <= alpha,
+        };
+        if usable {
+            if !end_reached {
+                search.horizon_positions.fetch_add(1, Ordering::Relaxed);
+            }
+            return score;
+        }
+    }
+    let horizon_before = search.horizon_positions.load(Ordering::Relaxed);
+
+    let (lower, upper) = search.bounds;
+    let mut remaining_prob = 1.0;
     let mut weighted_score_sum = 0.0;
 
     // check every possible next card
-    for (card, prob, next_state) in state.draw_cards() {
-        let (_, score) = search_explore_move(
-            &next_state,
-            card,
-            false,
-            depth - 1,
-            statistics,
-            timeout_reached,
-            false,
-        );
+    for (card, prob, mut next_state) in state.draw_cards() {
+        // rounding errors must not make the bounds tighter
+        remaining_prob = (remaining_prob - prob).max(0.0);
+        let card_alpha = (alpha - weighted_score_sum - remaining_prob * upper) / prob;
+        let card_beta = (beta - weighted_score_sum - remaining_prob * lower) / prob;
+
+        let score = match card {
+            Some(card) => {
+                search_explore_move(&next_state, card, depth - 1, search, card_alpha, card_beta)
+            }
+            // nothing is placed for a ruin, the next card is drawn right away
+            None => search_game_move(&mut next_state, depth, search, card_alpha, card_beta),
+        };
         weighted_score_sum += score * prob;
+
+        if score <= card_alpha {
+            let score = weighted_score_sum + remaining_prob * upper;
+            return search.store(key, depth, horizon_before, score, Bound::Upper);
+        }
+        if score >= card_beta {
+            let score = weighted_score_sum + remaining_prob * lower;
+            return search.store(key, depth, horizon_before, score, Bound::Lower);
+        }
     }
 
-    weighted_score_sum
+    search.store(key, depth, horizon_before, weighted_score_sum, Bound::Exact)
 }
 
-impl GameState<'_> {
+impl<'a> GameState<'a> {
     fn add_gold(&self, gold: i16) -> Self {
         Self {
             gold: self.gold + gold,
@@ -299,20 +747,20 @@ impl GameState<'_> {
     fn place_cells(&self, terrain: PlayerTerrain, cells: Mask) -> Self {
         Self {
             board: self.board.place_cells(terrain, cells),
+            // an ambush does not cover the ruin, the next explore card still has to
+            on_ruin: self.on_ruin && terrain == PlayerTerrain::Monster,
             ..*self
         }
     }
 
     // returns false only when the game is finished
-    fn handle_season_end(&mut self) -> bool {
+    pub fn handle_season_end(&mut self) -> bool {
         if self.season_timer < self.season.time() {
             // season still ongoing
             return true;
         }
 
-        for (scoring, _) in self.initial_state.scoring[self.season] {
-            self.total_score += scoring(self);
-        }
+        self.total_score += self.season_score().total();
 
         if let Some(season) = self.season.next() {
             self.new_season(season);
@@ -323,11 +771,59 @@ impl GameState<'_> {
         }
     }
 
-    fn final_score(&self) -> f32 {
+    // identifies the position, everything the score depends on
+    pub fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.board.hash(&mut hasher);
+        self.deck.hash(&mut hasher);
+        self.season.hash(&mut hasher);
+        self.season_timer.hash(&mut hasher);
+        self.gold.hash(&mut hasher);
+        self.total_score.hash(&mut hasher);
+        self.on_ruin.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // bounds of every score which can be reached from this state
+    fn score_bounds(&self) -> (f32, f32) {
+        // cells only get filled, so monsters never cost more than the cells empty now, and each
+        // gold is placed together with at least one of them
+        let empty = self.empty().count_cells() as f32;
+        let max_gold = (self.gold + self.mountain().count_cells()) as f32 + empty;
+        // no degree gives more than three points per cell or any negative points
+        let max_degree = 3.0 * Mask::CELL_COUNT as f32;
+
+        // the heuristic weighs four degrees, gold and monsters
+        let (min_heuristic, max_heuristic) = self
+            .initial_state
+            .scoring
+            .values()
+            .map(|scoring| {
+                let degree_factor = scoring[..4].iter().map(|(_, factor)| factor).sum::<f32>();
+                let (gold_factor, monster_factor) = (scoring[4].1, scoring[5].1);
+                (
+                    -monster_factor * empty,
+                    degree_factor * max_degree + gold_factor * max_gold,
+                )
+            })
+            .fold((0.0, 0.0), |(min, max), (lower, upper)| {
+                (f32::min(min, lower), f32::max(max, upper))
+            });
+        let seasons_left = (Season::Winter as usize - self.season as usize + 1) as f32;
+
+        // the remaining seasons (two degrees, gold and monsters each) and the heuristic
+        let lower = self.total_score as f32 - seasons_left * empty + min_heuristic;
+        let upper =
+            self.total_score as f32 + seasons_left * (2.0 * max_degree + max_gold) + max_heuristic;
+
+        (lower, upper)
+    }
+
+    pub fn final_score(&self) -> f32 {
         self.total_score as f32
     }
 
-    fn heuristic_score(&self) -> f32 {
+    pub fn heuristic_score(&self) -> f32 {
         self.total_score as f32
             + self.initial_state.scoring[self.season]
                 .iter()
@@ -335,18 +831,25 @@ impl GameState<'_> {
                 .sum::<f32>()
     }
 
-    fn draw_cards(&self) -> impl Iterator<Item = (&ExploreCard, f32, GameState<'_>)> {
+    // the probabilities sum up to one, the card is None for a ruin
+    pub fn draw_cards(
+        &self,
+    ) -> impl Iterator<Item = (Option<&'a ExploreCard>, f32, GameState<'a>)> + '_ {
         self.deck.draw_cards().map(|(c, prob, deck)| {
+            let mut new_state = *self;
+            new_state.deck = deck;
+
             let card = match c {
                 DrawnCard::ExploreCard(idx) => &self.initial_state.deck[idx as usize],
+                DrawnCard::Ruin => {
+                    new_state.on_ruin = true;
+                    return (None, prob, new_state);
+                }
                 DrawnCard::MonsterCard(idx) => &self.initial_state.monster_deck[idx as usize],
             };
-
-            let mut new_state = *self;
-            new_state.deck = deck;
             new_state.season_timer += card.time();
 
-            (card, prob, new_state)
+            (Some(card), prob, new_state)
         })
     }
 }
@@ -395,3 +898,60 @@ impl Board for GameState<'_> {
         self.initial_state.ruin
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names::DEGREES;
+
+    const LIMITS: SearchLimits = SearchLimits {
+        duration: Duration::from_secs(60),
+        max_depth: Some(1),
+        max_positions: None,
+        threads: 1,
+        engine: Engine::Expectimax,
+        ambush: AmbushModel::WorstCase,
+    };
+
+    #[test]
+    fn second_turn_keeps_its_score_without_root_pruning() {
+        let degrees = [0, 1, 2, 3].map(|i| DEGREES[i][0].scoring);
+        let initial_state = InitialState::new(degrees, Map::side_a());
+        let mut state = GameState::new(&initial_state);
+        state.new_season(Season::Winter);
+        state.set_season_timer(4);
+        // only the top rows are left, so a search of depth 1 is quick
+        state.new_board(PlayerBoard::new_with(|idx| {
+            (idx >= 3 * Mask::SIZE).then_some(PlayerTerrain::Forest)
+        }));
+
+        let card = "ackerland";
+        let (ranking, _) = find_best_moves(&state, card, &LIMITS, &TranspositionTable::new());
+        let (pruned, _) = rank_turns(&state, card, &LIMITS, &TranspositionTable::new(), true);
+        assert_eq!(pruned[0], ranking[0]);
+
+        // searched alone, the second turn has no better score to be cut off by
+        let (second, _) = ranking[1];
+        let mut next_state = None;
+        let card = initial_state
+            .deck
+            .iter()
+            .find(|c| c.name() == card)
+            .unwrap();
+        for_each_turn(&state, card, |turn, state| {
+            if turn == second {
+                next_state = Some(state);
+            }
+        });
+        let turns = [(second, next_state.unwrap())];
+        let (alone, _) = search_expectimax(
+            &state,
+            &turns,
+            false,
+            &LIMITS,
+            &TranspositionTable::new(),
+            true,
+        );
+        assert_eq!(alone, ranking[1..2]);
+    }
+}
@@ -0,0 +1,117 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    card::{explore_cards, monster_cards},
+    game::Season,
+    map::Map,
+    names::{self, Degree},
+    scoring::Score,
+    search::{find_best_move, GameState, InitialState, SearchLimits, SeasonScore},
+    transposition::TranspositionTable,
+};
+
+pub struct GameResult {
+    // the degrees A to D
+    pub degrees: [Degree; 4],
+    pub season_scores: Vec<(Season, SeasonScore)>,
+}
+
+impl GameResult {
+    pub fn score(&self) -> Score {
+        self.season_scores.iter().map(|(_, s)| s.total()).sum()
+    }
+}
+
+// plays a whole game on our own board without a server, all random decisions are made by the seed
+pub fn play_game(map: Map, seed: u64, limits: &SearchLimits) -> GameResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // one degree of each category, in random order
+    let mut categories = names::DEGREES;
+    categories.shuffle(&mut rng);
+    let degrees = categories.map(|degrees| *degrees.choose(&mut rng).unwrap());
+
+    let initial_state = InitialState::new(degrees.map(|d| d.scoring), map);
+    let mut state = GameState::new(&initial_state);
+    let table = TranspositionTable::new();
+
+    // one additional ambush is shuffled into the deck every season, drawn ambushes are removed
+    let mut ambush_pile: Vec<_> = monster_cards()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    ambush_pile.shuffle(&mut rng);
+    let mut ambushes_in_deck = Vec::new();
+
+    let mut season_scores = Vec::new();
+    let mut next_season = Some(Season::Spring);
+
+    while let Some(season) = next_season {
+        state.new_season(season);
+        ambushes_in_deck.extend(ambush_pile.pop());
+
+        let mut deck: Vec<_> = explore_cards()
+            .iter()
+            .map(|c| c.name().to_string())
+            .chain(names::RUIN_CARDS.iter().map(|c| c.id.to_string()))
+            .chain(ambushes_in_deck.iter().cloned())
+            .collect();
+        deck.shuffle(&mut rng);
+
+        while state.season_timer() < season.time() {
+            let card = deck.pop().expect("deck ran out of cards");
+            // the state remembers a ruin for the next explore card
+            let is_ambush = match state.reveal_card(&card).expect("card of the deck") {
+                Some(card) => card.is_ambush(),
+                None => continue,
+            };
+
+            let (turn, _) = find_best_move(&state, &card, limits, &table);
+            state.apply_turn(&turn);
+
+            if is_ambush {
+                ambushes_in_deck.retain(|c| *c != card);
+            }
+        }
+
+        season_scores.push((season, state.season_score()));
+        next_season = season.next();
+    }
+
+    GameResult {
+        degrees,
+        season_scores,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{ambush::AmbushModel, search::Engine};
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        // limited by positions on one thread, like a simulation without a think time
+        let limits = SearchLimits {
+            duration: Duration::from_secs(60 * 60),
+            max_depth: None,
+            max_positions: Some(2000),
+            threads: 1,
+            engine: Engine::Expectimax,
+            ambush: AmbushModel::WorstCase,
+        };
+
+        let [first, second] = [(); 2].map(|_| {
+            let result = play_game(Map::side_a(), 7, &limits);
+            let scores: Vec<_> = result
+                .season_scores
+                .iter()
+                .map(|(season, score)| (*season, score.total()))
+                .collect();
+            (result.degrees.map(|d| d.name.id), scores)
+        });
+        assert_eq!(first, second);
+    }
+}
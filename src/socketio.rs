@@ -1,27 +1,286 @@
-use std::io::{Read, Write};
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+    net::TcpStream,
+    str::FromStr,
+    time::Duration,
+};
 
-use tungstenite::{client::IntoClientRequest, Message, WebSocket};
+use tungstenite::{
+    client::IntoClientRequest, http::Uri, stream::MaybeTlsStream, Message, WebSocket,
+};
 
-pub fn connect(req: impl IntoClientRequest) -> Result<impl SocketIOExt> {
-    let mut socket = connect_engineio(req)?;
-    connect_socketio(&mut socket)?;
+use crate::polling::Polling;
+
+// a connection over one of the engine.io transports
+pub enum Socket {
+    Websocket(WebSocket<MaybeTlsStream<TcpStream>>, Handshake),
+    Polling(Polling),
+}
+
+impl Socket {
+    pub fn handshake(&self) -> &Handshake {
+        match self {
+            Self::Websocket(_, handshake) => handshake,
+            Self::Polling(polling) => polling.handshake(),
+        }
+    }
+
+    // no packets for longer than the ping interval and timeout means that the server is gone
+    fn dead_connection(&self, err: Error) -> Error {
+        let kind = match &err {
+            Error::Io(io_err) => Some(io_err.kind()),
+            Error::Websocket(ws_err) => match ws_err.as_ref() {
+                tungstenite::Error::Io(io_err) => Some(io_err.kind()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if matches!(
+            kind,
+            Some(std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+        ) {
+            Error::EngineIO(format!(
+                "no ping received within {:?}",
+                self.handshake().read_timeout()
+            ))
+        } else {
+            err
+        }
+    }
+}
+
+// the parameters of an engine.io session, sent by the server in the open packet
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    pub sid: String,
+    pub upgrades: Vec<String>,
+    pub ping_interval: Duration,
+    pub ping_timeout: Duration,
+    pub max_payload: usize,
+}
+
+impl Handshake {
+    // missing parameters are set to the defaults of engine.io
+    pub fn from_open_packet(msg: &str) -> Result<Self> {
+        let data: serde_json::Value = match msg.strip_prefix('0') {
+            Some(data) => serde_json::from_str(data)?,
+            None => {
+                return Err(Error::EngineIO(format!(
+                    "expected open packet, instead got {}",
+                    msg
+                )))
+            }
+        };
+
+        let millis = |name, default| {
+            Duration::from_millis(data.get(name).and_then(|v| v.as_u64()).unwrap_or(default))
+        };
+
+        Ok(Self {
+            sid: data
+                .get("sid")
+                .and_then(|sid| sid.as_str())
+                .ok_or_else(|| Error::EngineIO("missing session id in open packet".into()))?
+                .into(),
+            upgrades: data
+                .get("upgrades")
+                .and_then(|upgrades| upgrades.as_array())
+                .map(|upgrades| {
+                    upgrades
+                        .iter()
+                        .filter_map(|u| u.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ping_interval: millis("pingInterval", 25000),
+            ping_timeout: millis("pingTimeout", 20000),
+            max_payload: data
+                .get("maxPayload")
+                .and_then(|v| v.as_u64())
+                .map_or(1_000_000, |v| v as usize),
+        })
+    }
+
+    // the server pings once per interval, so a longer silence means the connection is dead
+    pub fn read_timeout(&self) -> Duration {
+        self.ping_interval + self.ping_timeout
+    }
+}
+
+// how the connection to the server is established
+#[derive(Debug, Clone, Copy)]
+pub enum Transport {
+    Websocket,
+    Polling,
+    // start with long-polling and switch to a websocket if the server allows it
+    Upgrade,
+}
+
+impl FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "websocket" => Ok(Self::Websocket),
+            "polling" => Ok(Self::Polling),
+            "upgrade" => Ok(Self::Upgrade),
+            _ => Err(format!("unknown transport {}", s)),
+        }
+    }
+}
+
+// connects to the given namespace, all events have to be sent through a `SocketIOExt` which
+// returns the same namespace
+pub fn connect(uri: &Uri, namespace: &str, transport: Transport) -> Result<Socket> {
+    let mut socket = match transport {
+        Transport::Websocket => {
+            let (socket, handshake) = connect_engineio(uri)?;
+            Socket::Websocket(socket, handshake)
+        }
+        Transport::Polling => Socket::Polling(Polling::connect(uri)?),
+        Transport::Upgrade => {
+            let polling = Polling::connect(uri)?;
+            match polling.upgrade() {
+                Ok(socket) => Socket::Websocket(socket, polling.handshake().clone()),
+                Err(err) => {
+                    println!("Staying on long-polling, upgrade failed: {}", err);
+                    Socket::Polling(polling)
+                }
+            }
+        }
+    };
+
+    if let Socket::Websocket(socket, handshake) = &mut socket {
+        let timeout = Some(handshake.read_timeout());
+        match socket.get_mut() {
+            MaybeTlsStream::Plain(stream) => stream.set_read_timeout(timeout)?,
+            MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(timeout)?,
+            _ => {}
+        }
+    }
+
+    connect_socketio(&mut socket, namespace)?;
 
     Ok(socket)
 }
 
+// builds the websocket URI from a full ws:// or wss:// URL, or from only a host with an optional port
+pub fn server_uri(url: &str) -> Result<Uri> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("ws://{}", url)
+    };
+
+    let uri: Uri = url
+        .parse()
+        .map_err(|err| Error::Url(format!("invalid URL {}: {}", url, err)))?;
+
+    if !matches!(
+        uri.scheme_str(),
+        Some("ws") | Some("wss") | Some("http") | Some("https")
+    ) {
+        return Err(Error::Url(format!(
+            "unsupported scheme {:?}",
+            uri.scheme_str()
+        )));
+    }
+
+    transport_uri(&uri, "websocket", None)
+}
+
+// the URI of an engine.io transport, http(s) for polling and ws(s) for websockets, with the
+// default path if there is none
+pub fn transport_uri(uri: &Uri, transport: &str, sid: Option<&str>) -> Result<Uri> {
+    let secure = matches!(uri.scheme_str(), Some("wss") | Some("https"));
+    let scheme = match (transport, secure) {
+        ("polling", false) => "http",
+        ("polling", true) => "https",
+        (_, false) => "ws",
+        (_, true) => "wss",
+    };
+
+    let authority = uri
+        .authority()
+        .ok_or_else(|| Error::Url(format!("missing host in URL {}", uri)))?;
+
+    let path = match uri.path() {
+        "" | "/" => "/socket.io/",
+        path => path,
+    };
+
+    // keep all query parameters, but make sure engine.io uses version 4 over the transport
+    let mut query: Vec<_> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|param| {
+            !param.is_empty()
+                && !param.starts_with("EIO=")
+                && !param.starts_with("transport=")
+                && !param.starts_with("sid=")
+        })
+        .map(String::from)
+        .collect();
+    query.push("EIO=4".into());
+    query.push(format!("transport={}", transport));
+    if let Some(sid) = sid {
+        query.push(format!("sid={}", sid));
+    }
+
+    Uri::builder()
+        .scheme(scheme)
+        .authority(authority.clone())
+        .path_and_query(format!("{}?{}", path, query.join("&")))
+        .build()
+        .map_err(|err| Error::Url(format!("invalid URL {}: {}", uri, err)))
+}
+
 #[derive(Debug)]
 pub enum Error {
-    Websocket(tungstenite::Error),
+    Url(String),
+    Io(std::io::Error),
+    Http(String),
+    Websocket(Box<tungstenite::Error>),
     EngineIO(String),
     SocketIO(String),
     Json(serde_json::Error),
+    // the message was not sent, the connection is still intact
+    PayloadTooLarge { size: usize, max: usize },
 }
 
-type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(msg) => write!(f, "{}", msg),
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Http(msg) => write!(f, "http error: {}", msg),
+            Self::Websocket(err) => write!(f, "websocket error: {}", err),
+            Self::EngineIO(msg) => write!(f, "engine.io error: {}", msg),
+            Self::SocketIO(msg) => write!(f, "socket.io error: {}", msg),
+            Self::Json(err) => write!(f, "json error: {}", err),
+            Self::PayloadTooLarge { size, max } => write!(
+                f,
+                "packet of {} bytes exceeds the maximum payload of {} bytes",
+                size, max
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
 
 impl From<tungstenite::Error> for Error {
     fn from(err: tungstenite::Error) -> Self {
-        Self::Websocket(err)
+        Self::Websocket(Box::new(err))
     }
 }
 
@@ -31,25 +290,26 @@ impl From<serde_json::Error> for Error {
     }
 }
 
-fn connect_engineio(req: impl IntoClientRequest) -> Result<WebSocket<impl Read + Write>> {
+fn connect_engineio(
+    req: impl IntoClientRequest,
+) -> Result<(WebSocket<MaybeTlsStream<TcpStream>>, Handshake)> {
     let (mut socket, _) = tungstenite::connect(req)?;
+    let handshake = Handshake::from_open_packet(&socket.read_raw_message()?)?;
 
-    match socket.read_engineio_packet()? {
-        EngineIOPacket('0', _) => Ok(socket),
-        p => Err(Error::EngineIO(format!(
-            "expected open packet, instead got {:?}",
-            p
-        ))),
-    }
+    Ok((socket, handshake))
 }
 
-fn connect_socketio(socket: &mut impl SocketIOExt) -> Result<()> {
+fn connect_socketio(socket: &mut impl SocketIOExt, namespace: &str) -> Result<()> {
     // send connect request
-    socket.write_socketio_packet("0")?;
+    socket.write_socketio_packet(&SocketIOPacket::new('0', namespace, None, String::new()))?;
 
     // expect connect response
     match socket.read_socketio_packet()? {
-        SocketIOPacket('0', _) => Ok(()),
+        p if p.kind == '0' && p.namespace == namespace => Ok(()),
+        p if p.kind == '4' => Err(Error::SocketIO(format!(
+            "connection to namespace {} refused: {}",
+            namespace, p.payload
+        ))),
         p => Err(Error::SocketIO(format!(
             "expected connect package, instead got {:?}",
             p
@@ -58,16 +318,160 @@ fn connect_socketio(socket: &mut impl SocketIOExt) -> Result<()> {
 }
 
 #[derive(Debug)]
-pub struct EngineIOPacket(char, String);
+pub struct EngineIOPacket(pub char, pub String);
 
+// a decoded socket.io packet, the payload is json (or empty)
 #[derive(Debug)]
-pub struct SocketIOPacket(char, String);
+pub struct SocketIOPacket {
+    pub kind: char,
+    pub namespace: String,
+    pub ack_id: Option<u64>,
+    pub payload: String,
+    pub attachments: Vec<Vec<u8>>,
+}
+
+impl SocketIOPacket {
+    pub fn new(kind: char, namespace: &str, ack_id: Option<u64>, payload: String) -> Self {
+        Self {
+            kind,
+            namespace: namespace.into(),
+            ack_id,
+            payload,
+            attachments: Vec::new(),
+        }
+    }
+
+    // format: <type>[<attachment count>-][<namespace>,][<ack id>][<payload>]
+    // returns the packet and the number of binary attachments following it
+    fn decode(msg: &str) -> Result<(Self, usize)> {
+        let invalid = || Error::SocketIO(format!("invalid package format {}", msg));
+
+        let kind = msg.chars().next().ok_or_else(invalid)?;
+        let mut rest = &msg[kind.len_utf8()..];
+
+        let mut attachment_count = 0;
+        if kind == '5' || kind == '6' {
+            let (count, r) = rest.split_once('-').ok_or_else(invalid)?;
+            attachment_count = count.parse().map_err(|_| invalid())?;
+            rest = r;
+        }
+
+        let mut namespace = "/";
+        if rest.starts_with('/') {
+            let end = rest.find(',').unwrap_or(rest.len());
+            namespace = &rest[..end];
+            rest = rest.get(end + 1..).unwrap_or("");
+        }
+
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let ack_id = if digits > 0 {
+            Some(rest[..digits].parse().map_err(|_| invalid())?)
+        } else {
+            None
+        };
+
+        let packet = Self::new(kind, namespace, ack_id, rest[digits..].into());
+        Ok((packet, attachment_count))
+    }
+
+    // the attachments are not part of the message, they are sent as binary messages after it
+    fn encode(&self) -> String {
+        // events and acknowledgements with attachments are sent as their binary variants
+        let kind = match (self.kind, self.attachments.is_empty()) {
+            ('2', false) => '5',
+            ('3', false) => '6',
+            (kind, _) => kind,
+        };
+
+        let mut msg = String::new();
+        msg.push(kind);
+
+        if !self.attachments.is_empty() {
+            msg.push_str(&format!("{}-", self.attachments.len()));
+        }
+
+        if self.namespace != "/" {
+            msg.push_str(&self.namespace);
+            msg.push(',');
+        }
+
+        if let Some(id) = self.ack_id {
+            msg.push_str(&id.to_string());
+        }
+
+        msg.push_str(&self.payload);
+        msg
+    }
+}
+
+// replaces the placeholders of binary attachments with arrays of the bytes
+fn fill_placeholders(value: &mut serde_json::Value, attachments: &[Vec<u8>]) {
+    match value {
+        serde_json::Value::Object(obj) if obj.get("_placeholder") == Some(&true.into()) => {
+            let data = obj
+                .get("num")
+                .and_then(|num| num.as_u64())
+                .and_then(|num| attachments.get(num as usize));
+            if let Some(data) = data {
+                *value = data.iter().map(|&b| serde_json::Value::from(b)).collect();
+            }
+        }
+        serde_json::Value::Object(obj) => {
+            for v in obj.values_mut() {
+                fill_placeholders(v, attachments);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for v in values {
+                fill_placeholders(v, attachments);
+            }
+        }
+        _ => {}
+    }
+}
 
 pub trait SocketIOExt {
     fn read_raw_message(&mut self) -> Result<String>;
 
     fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()>;
 
+    // binary attachments of socket.io packets
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        Err(Error::EngineIO("binary messages are not supported".into()))
+    }
+
+    fn write_raw_binary(&mut self, _data: &[u8]) -> Result<()> {
+        Err(Error::EngineIO("binary messages are not supported".into()))
+    }
+
+    // the namespace used for all events
+    fn namespace(&self) -> &str {
+        "/"
+    }
+
+    // called when the server closes the connection or disconnects the namespace, reading continues
+    // if this succeeds
+    fn on_disconnect(&mut self) -> Result<()> {
+        Err(Error::SocketIO("disconnected by the server".into()))
+    }
+
+    // called when the server acknowledges an event sent with `write_json_event_with_ack`
+    fn on_ack(&mut self, _id: u64, _data: serde_json::Value) -> Result<()> {
+        Ok(())
+    }
+
+    // called for every event which is received or sent
+    fn on_event(
+        &mut self,
+        _sent: bool,
+        _event: &str,
+        _data: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn read_engineio_packet(&mut self) -> Result<EngineIOPacket> {
         let mut msg = self.read_raw_message()?;
         let t = msg
@@ -84,7 +488,7 @@ pub trait SocketIOExt {
 
     fn read_socketio_packet(&mut self) -> Result<SocketIOPacket> {
         loop {
-            let EngineIOPacket(t, mut msg) = self.read_engineio_packet()?;
+            let EngineIOPacket(t, msg) = self.read_engineio_packet()?;
             match t {
                 // ping
                 '2' => {
@@ -92,49 +496,90 @@ pub trait SocketIOExt {
                 }
                 // message
                 '4' => {
-                    let t = msg
-                        .drain(0..1)
-                        .next()
-                        .ok_or_else(|| Error::SocketIO("invalid package format".into()))?;
-                    return Ok(SocketIOPacket(t, msg));
+                    let (mut packet, attachment_count) = SocketIOPacket::decode(&msg)?;
+                    for _ in 0..attachment_count {
+                        packet.attachments.push(self.read_raw_binary()?);
+                    }
+                    return Ok(packet);
                 }
                 // noop
                 '6' => {}
-                // open, close, pong, upgrade
+                // close
+                '1' => self.on_disconnect()?,
+                // open, pong, upgrade
                 _ => return Err(Error::EngineIO(format!("unexpected packet of type {}", t))),
             }
         }
     }
 
-    fn write_socketio_packet(&mut self, msg: impl Into<String>) -> Result<()> {
+    fn write_socketio_packet(&mut self, packet: &SocketIOPacket) -> Result<()> {
         // transform into an engine.io message packet
-        let mut msg = msg.into();
+        let mut msg = packet.encode();
         msg.insert(0, '4');
-        self.write_engineio_packet(msg)
+        self.write_engineio_packet(msg)?;
+
+        for attachment in &packet.attachments {
+            self.write_raw_binary(attachment)?;
+        }
+
+        Ok(())
     }
 
     fn read_event(&mut self) -> Result<(String, Option<serde_json::Value>)> {
-        let payload = {
-            let SocketIOPacket(t, msg) = self.read_socketio_packet()?;
-            match t {
-                // event
-                '2' => msg,
+        loop {
+            let packet = self.read_socketio_packet()?;
+            if packet.namespace != self.namespace() {
+                continue;
+            }
+
+            let mut payload = match packet.kind {
+                // event, binary event
+                '2' | '5' => serde_json::from_str(&packet.payload)?,
+                // ack, binary ack
+                '3' | '6' => {
+                    if let Some(id) = packet.ack_id {
+                        let mut data: serde_json::Value = serde_json::from_str(&packet.payload)?;
+                        fill_placeholders(&mut data, &packet.attachments);
+                        self.on_ack(id, data)?;
+                    }
+                    continue;
+                }
+                // disconnect
+                '1' => {
+                    self.on_disconnect()?;
+                    continue;
+                }
                 // other
-                _ => return Err(Error::SocketIO(format!("unexpected packet of type {}", t))),
+                t => return Err(Error::SocketIO(format!("unexpected packet of type {}", t))),
+            };
+
+            fill_placeholders(&mut payload, &packet.attachments);
+
+            if let Some(id) = packet.ack_id {
+                // the server waits for an acknowledgement
+                let namespace = self.namespace().to_string();
+                self.write_socketio_packet(&SocketIOPacket::new(
+                    '3',
+                    &namespace,
+                    Some(id),
+                    "[]".into(),
+                ))?;
             }
-        };
 
-        if let serde_json::Value::Array(elems) = serde_json::from_str(&payload)? {
-            let mut elems = elems.into_iter();
-            if let Some(serde_json::Value::String(event)) = elems.next() {
-                return Ok((event, elems.next()));
+            if let serde_json::Value::Array(elems) = payload {
+                let mut elems = elems.into_iter();
+                if let Some(serde_json::Value::String(event)) = elems.next() {
+                    let data = elems.next();
+                    self.on_event(false, &event, data.as_ref())?;
+                    return Ok((event, data));
+                }
             }
-        }
 
-        Err(Error::SocketIO(format!(
-            "could not parse event payload {}",
-            payload
-        )))
+            return Err(Error::SocketIO(format!(
+                "could not parse event payload {}",
+                packet.payload
+            )));
+        }
     }
 
     fn expect_event(&mut self, event: &str) -> Result<Option<serde_json::Value>> {
@@ -158,11 +603,30 @@ pub trait SocketIOExt {
         event: impl Into<String>,
         data: &serde_json::Value,
     ) -> Result<()> {
-        let msg = format!(
-            "2{}",
-            serde_json::to_string(&[&serde_json::Value::String(event.into()), data])?
-        );
-        self.write_socketio_packet(msg)
+        self.write_event_packet(event.into(), data, None)
+    }
+
+    // the server answers with an acknowledgement of the same id, see `on_ack`
+    fn write_json_event_with_ack(
+        &mut self,
+        event: impl Into<String>,
+        data: &serde_json::Value,
+        ack_id: u64,
+    ) -> Result<()> {
+        self.write_event_packet(event.into(), data, Some(ack_id))
+    }
+
+    fn write_event_packet(
+        &mut self,
+        event: String,
+        data: &serde_json::Value,
+        ack_id: Option<u64>,
+    ) -> Result<()> {
+        self.on_event(true, &event, Some(data))?;
+
+        let payload = serde_json::to_string(&[&serde_json::Value::String(event), data])?;
+        let packet = SocketIOPacket::new('2', self.namespace(), ack_id, payload);
+        self.write_socketio_packet(&packet)
     }
 }
 
@@ -183,4 +647,112 @@ where
     fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
         Ok(self.write_message(Message::Text(msg.into()))?)
     }
+
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        match self.read_message()? {
+            Message::Binary(data) => Ok(data),
+            msg => Err(Error::EngineIO(format!(
+                "expected a binary websocket message, got {}",
+                msg
+            ))),
+        }
+    }
+
+    fn write_raw_binary(&mut self, data: &[u8]) -> Result<()> {
+        Ok(self.write_message(Message::Binary(data.to_vec()))?)
+    }
+}
+
+impl SocketIOExt for Socket {
+    fn read_raw_message(&mut self) -> Result<String> {
+        match self {
+            Self::Websocket(socket, _) => socket.read_raw_message(),
+            Self::Polling(polling) => polling.read_raw_message(),
+        }
+        .map_err(|err| self.dead_connection(err))
+    }
+
+    fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+        // the server closes the connection if a packet is too large
+        let msg = msg.into();
+        let max_payload = self.handshake().max_payload;
+        if msg.len() > max_payload {
+            return Err(Error::PayloadTooLarge {
+                size: msg.len(),
+                max: max_payload,
+            });
+        }
+
+        match self {
+            Self::Websocket(socket, _) => socket.write_raw_message(msg),
+            Self::Polling(polling) => polling.write_raw_message(msg),
+        }
+    }
+
+    fn read_raw_binary(&mut self) -> Result<Vec<u8>> {
+        match self {
+            Self::Websocket(socket, _) => socket.read_raw_binary(),
+            Self::Polling(polling) => polling.read_raw_binary(),
+        }
+        .map_err(|err| self.dead_connection(err))
+    }
+
+    fn write_raw_binary(&mut self, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Websocket(socket, _) => socket.write_raw_binary(data),
+            Self::Polling(polling) => polling.write_raw_binary(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // collects the written messages
+    #[derive(Default)]
+    struct Written {
+        messages: Vec<String>,
+        binaries: Vec<Vec<u8>>,
+    }
+
+    impl SocketIOExt for Written {
+        fn read_raw_message(&mut self) -> Result<String> {
+            Err(Error::EngineIO("nothing to read".into()))
+        }
+
+        fn write_raw_message(&mut self, msg: impl Into<String>) -> Result<()> {
+            self.messages.push(msg.into());
+            Ok(())
+        }
+
+        fn write_raw_binary(&mut self, data: &[u8]) -> Result<()> {
+            self.binaries.push(data.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn binary_event_is_followed_by_its_attachments() {
+        let payload = r#"["upload",{"_placeholder":true,"num":0}]"#.to_string();
+        let mut packet = SocketIOPacket::new('2', "/game", Some(7), payload.clone());
+        packet.attachments.push(vec![1, 2, 3]);
+
+        let mut written = Written::default();
+        written.write_socketio_packet(&packet).unwrap();
+
+        assert_eq!(written.messages, [format!("451-/game,7{}", payload)]);
+        assert_eq!(written.binaries, [vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn binary_event_round_trip() {
+        let mut packet = SocketIOPacket::new('2', "/", None, "[\"x\"]".into());
+        packet.attachments.push(vec![0]);
+
+        let (decoded, attachment_count) = SocketIOPacket::decode(&packet.encode()).unwrap();
+        assert_eq!(decoded.kind, '5');
+        assert_eq!(decoded.payload, "[\"x\"]");
+        assert_eq!(attachment_count, 1);
+    }
 }
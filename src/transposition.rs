@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// number of entries, 16 bytes each
+const TABLE_SIZE: usize = 1 << 20;
+
+// stored instead of the depth if every line of the search reached the end of the game, the score
+// is the same for any depth then
+const END_DEPTH: u32 = (1 << 30) - 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the score is at least the stored one
+    Lower,
+    // the score is at most the stored one
+    Upper,
+}
+
+// a fixed size cache of evaluated positions, shared by all search threads without locking
+pub struct TranspositionTable {
+    // per entry the key xor the data and the data, so an entry torn by concurrent writes is
+    // detected as a different key
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self {
+            entries: (0..TABLE_SIZE)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    // the score of the position, what it tells about the real score and whether the end of the
+    // game was reached, if the position was evaluated with at least the given depth
+    pub fn get(&self, key: u64, depth: u32) -> Option<(f32, Bound, bool)> {
+        let [checked_key, data] = &self.entries[key as usize % TABLE_SIZE];
+        let data = data.load(Ordering::Relaxed);
+        if checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+
+        let (entry_depth, score) = ((data >> 34) as u32, f32::from_bits(data as u32));
+        let bound = match (data >> 32) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        (entry_depth >= depth).then_some((score, bound, entry_depth == END_DEPTH))
+    }
+
+    // always replaces the previous entry
+    pub fn insert(&self, key: u64, depth: u32, end_reached: bool, score: f32, bound: Bound) {
+        let depth = if end_reached { END_DEPTH } else { depth };
+        let data = (depth as u64) << 34 | (bound as u64) << 32 | score.to_bits() as u64;
+        let [checked_key, entry_data] = &self.entries[key as usize % TABLE_SIZE];
+        checked_key.store(key ^ data, Ordering::Relaxed);
+        entry_data.store(data, Ordering::Relaxed);
+    }
+}